@@ -1,4 +1,22 @@
-use std::{collections::HashMap, fmt::Display, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Display,
+    rc::Rc,
+};
+
+use crate::{
+    env::{Binding, Environ},
+    error::{EvalError, EvalErrorKind, ParseError, ParseErrorKind},
+    estree::Node,
+    js, num,
+    options::{Options, Semantics},
+    span::Span,
+    store::Store,
+};
+
+mod gen;
+
+pub use gen::Generator;
 
 #[derive(Debug)]
 enum UnaryOp {
@@ -13,7 +31,7 @@ enum UnaryOp {
 }
 
 impl Display for UnaryOp {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use UnaryOp::*;
         match self {
             Plus => write!(f, "+"),
@@ -24,9 +42,8 @@ impl Display for UnaryOp {
     }
 }
 
-#[allow(dead_code)]
 #[derive(Debug)]
-enum BinOp {
+pub(crate) enum BinOp {
     // The `+` operator (addition)
     Add,
     // The `-` operator (subtraction)
@@ -51,14 +68,20 @@ enum BinOp {
     Shl,
     // The `>>` operator (shift right)
     Shr,
+    // The `>>>` operator (unsigned shift right)
+    UShr,
     // The `==` operator (equality)
     Eq,
+    // The `===` operator (strict equality)
+    StrictEq,
     // The `<` operator (less than)
     Lt,
     // The `<=` operator (less than or equal to)
     Le,
     // The `!=` operator (not equal to)
     Ne,
+    // The `!==` operator (strict inequality)
+    StrictNe,
     // The `>=` operator (greater than or equal to)
     Ge,
     // The `>` operator (greater than)
@@ -66,7 +89,7 @@ enum BinOp {
 }
 
 impl Display for BinOp {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use BinOp::*;
         match self {
             Add => write!(f, "+"),
@@ -79,12 +102,15 @@ impl Display for BinOp {
             BitXor => write!(f, "^"),
             BitAnd => write!(f, "&"),
             BitOr => write!(f, "|"),
-            Shl => write!(f, ">>"),
-            Shr => write!(f, "<<"),
+            Shl => write!(f, "<<"),
+            Shr => write!(f, ">>"),
+            UShr => write!(f, ">>>"),
             Eq => write!(f, "=="),
+            StrictEq => write!(f, "==="),
             Lt => write!(f, "<"),
             Le => write!(f, "<="),
             Ne => write!(f, "!="),
+            StrictNe => write!(f, "!=="),
             Ge => write!(f, ">="),
             Gt => write!(f, ">"),
         }
@@ -92,59 +118,67 @@ impl Display for BinOp {
 }
 
 #[derive(Debug)]
-struct BinaryExpr<'a> {
+struct BinaryExpr {
     op: BinOp,
-    lhs: Expr<'a>,
-    rhs: Expr<'a>,
+    lhs: Expr,
+    rhs: Expr,
 }
 
-impl BinaryExpr<'_> {
-    fn new(expr: &serde_json::Value) -> Self {
-        let op = match expr.get("operator") {
-            Some(op) => op.as_str().unwrap(),
-            None => todo!("none binop operator"),
+impl BinaryExpr {
+    fn new(expr: &Node) -> Result<Self, ParseError> {
+        let op = expr.str("operator")?;
+        let op = match BinOp::from_operator(op) {
+            Some(op) => op,
+            None => return Err(expr.error(ParseErrorKind::UnsupportedOperator(op.to_owned()))),
         };
 
+        Ok(BinaryExpr {
+            op,
+            lhs: Expr::new(&expr.get("left")?)?,
+            rhs: Expr::new(&expr.get("right")?)?,
+        })
+    }
+}
+
+impl BinOp {
+    fn from_operator(op: &str) -> Option<Self> {
         use BinOp::*;
-        let op = match op {
+        Some(match op {
             "+" => Add,
             "-" => Sub,
             "*" => Mul,
             "/" => Div,
             "%" => Rem,
+            "^" => BitXor,
+            "&" => BitAnd,
+            "|" => BitOr,
+            "<<" => Shl,
+            ">>" => Shr,
+            ">>>" => UShr,
             "&&" => And,
             "||" => Or,
             "==" => Eq,
             "!=" => Ne,
+            "===" => StrictEq,
+            "!==" => StrictNe,
             "<" => Lt,
             "<=" => Le,
             ">" => Gt,
             ">=" => Ge,
-            _ => unimplemented!("unimplemented binop: {}", op),
-        };
-        let lhs = expr.get("left").unwrap();
-        let rhs = expr.get("right").unwrap();
-
-        BinaryExpr {
-            op,
-            lhs: Expr::new(lhs),
-            rhs: Expr::new(rhs),
-        }
+            _ => return None,
+        })
     }
 }
 
 #[derive(Debug)]
-struct UnaryExpr<'a> {
+struct UnaryExpr {
     op: UnaryOp,
-    expr: Expr<'a>,
+    expr: Expr,
 }
 
-impl UnaryExpr<'_> {
-    fn new(expr: &serde_json::Value) -> Self {
-        let op = match expr.get("operator") {
-            Some(op) => op.as_str().unwrap(),
-            None => todo!("none binop operator"),
-        };
+impl UnaryExpr {
+    fn new(expr: &Node) -> Result<Self, ParseError> {
+        let op = expr.str("operator")?;
 
         use UnaryOp::*;
         let op = match op {
@@ -152,549 +186,3131 @@ impl UnaryExpr<'_> {
             "-" => Minus,
             "!" => Not,
             "~" => BitNot,
-            _ => unimplemented!("unimplemented unaryop: {}", op),
+            _ => {
+                return Err(expr.error(ParseErrorKind::UnsupportedOperator(op.to_owned())));
+            }
         };
 
-        let child_expr = expr.get("argument").unwrap();
-
-        UnaryExpr {
+        Ok(UnaryExpr {
             op,
-            expr: Expr::new(child_expr),
-        }
+            expr: Expr::new(&expr.get("argument")?)?,
+        })
     }
 }
 
 #[derive(Debug)]
-struct CondExpr<'a> {
-    test: Expr<'a>,
-    cons: Expr<'a>,
-    altr: Expr<'a>,
+struct CondExpr {
+    test: Expr,
+    cons: Expr,
+    altr: Expr,
 }
 
-impl CondExpr<'_> {
-    fn new(expr: &serde_json::Value) -> Self {
-        let test = expr.get("test").unwrap();
-        let cons = expr.get("consequent").unwrap();
-        let altr = expr.get("alternate").unwrap();
+impl CondExpr {
+    /// A `?:` expression, or an `if` statement, whose missing `else` does
+    /// nothing.
+    fn new(expr: &Node) -> Result<Self, ParseError> {
+        let altr = match expr.get_opt("alternate") {
+            Some(altr) => Expr::new(&altr)?,
+            None => Expr::unit(expr.span()),
+        };
+
+        Ok(CondExpr {
+            test: Expr::new(&expr.get("test")?)?,
+            cons: Expr::new(&expr.get("consequent")?)?,
+            altr,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Decl {
+    pattern: Pattern,
+    constant: bool,
+    init: Expr,
+}
+
+impl Decl {
+    /// The declarators of one `VariableDeclaration`.
+    fn from_declaration(decl: &Node) -> Result<Vec<Self>, ParseError> {
+        let constant = decl.str("kind")? == "const";
+        let mut decls = Vec::new();
+        for dec in decl.array("declarations")? {
+            let pattern = Pattern::new(&dec.get("id")?)?;
+            // `let x;` starts out undefined
+            let init = match dec.get_opt("init") {
+                Some(init) => Expr::new(&init)?,
+                None => Expr::unit(dec.span()),
+            };
 
-        CondExpr {
-            test: Expr::new(test),
-            cons: Expr::new(cons),
-            altr: Expr::new(altr),
+            decls.push(Decl {
+                pattern,
+                constant,
+                init,
+            });
         }
+        Ok(decls)
     }
 }
 
 #[derive(Debug)]
-struct BindExpr<'a> {
-    binds: Vec<(String, Expr<'a>)>,
-    body: Expr<'a>,
+struct BindExpr {
+    decls: Vec<Decl>,
+    body: Expr,
 }
 
 // TODO: don't particularly like this
-impl BindExpr<'_> {
-    fn new(expr: &serde_json::Value, rest: &[serde_json::Value]) -> Self {
+impl BindExpr {
+    /// The bindings of a run of consecutive declarations `decls`, which are
+    /// all in scope of each other, and `rest` as the body.
+    fn new(decls: &[Node], rest: &[Node]) -> Result<Self, ParseError> {
         let mut binds = Vec::new();
+        for decl in decls {
+            binds.extend(Decl::from_declaration(decl)?);
+        }
 
-        let declarations = expr.get("declarations").unwrap().as_array().unwrap();
-        for dec in declarations {
-            let ident = dec
-                .get("id")
-                .unwrap()
-                .get("name")
-                .unwrap()
-                .as_str()
-                .unwrap();
-            let init = dec.get("init").unwrap();
-            let init = Expr::new(init);
-
-            binds.push((ident.to_owned(), init));
-        }
-
-        let body = match rest.first() {
-            Some(expr) => match expr.get("type").unwrap().as_str().unwrap() {
-                "VariableDeclaration" => Expr::Bind(Box::new(BindExpr::new(expr, &rest[1..]))),
-                _ => Expr::new(expr),
-            },
-            None => unimplemented!("Err: BindExpr with no body expr"),
+        let body = match Expr::from_body(rest)? {
+            Some(body) => body,
+            None => Expr::unit(decls.last().and_then(Node::span)),
         };
 
-        BindExpr { binds, body }
+        Ok(BindExpr { decls: binds, body })
     }
 }
 
 #[derive(Debug)]
-struct FnExpr<'a> {
-    arg: String,
-    body: Expr<'a>,
+struct AssignExpr {
+    target: Target,
+    /// The operator of a compound assignment like `+=`.
+    op: Option<BinOp>,
+    value: Expr,
 }
-impl FnExpr<'_> {
-    fn new(expr: &serde_json::Value) -> FnExpr {
-        let arg = String::from(
-            expr.get("params")
-                .unwrap()
-                .as_array()
-                .unwrap()
-                .first()
-                .unwrap()
-                .get("name")
-                .unwrap()
-                .as_str()
-                .unwrap(),
-        );
-        let body = expr.get("body").unwrap().as_array().unwrap();
 
-        let body = match Expr::from_body(body) {
-            Some(expr) => expr,
-            None => panic!("FnExpr::unable to create expr from body"),
+impl AssignExpr {
+    fn new(expr: &Node) -> Result<Self, ParseError> {
+        let op = expr.str("operator")?;
+        let op = match op {
+            "=" => None,
+            _ => {
+                use BinOp::*;
+                match op.strip_suffix('=').and_then(BinOp::from_operator) {
+                    Some(
+                        op @ (Add | Sub | Mul | Div | Rem | BitXor | BitAnd | BitOr | Shl | Shr
+                        | UShr),
+                    ) => Some(op),
+                    _ => {
+                        return Err(expr.error(ParseErrorKind::UnsupportedOperator(op.to_owned())));
+                    }
+                }
+            }
         };
 
-        FnExpr { arg, body }
+        Ok(AssignExpr {
+            target: Target::new(&expr.get("left")?)?,
+            op,
+            value: Expr::new(&expr.get("right")?)?,
+        })
     }
 }
 
+/// `++` or `--`, before or after its operand.
 #[derive(Debug)]
-pub struct FnValue<'a> {
-    env: &'a Environ<'a>,
-    arg: String,
-    body: Box<&'a Expr<'a>>,
+struct UpdateExpr {
+    target: Target,
+    /// `Add` for `++` and `Sub` for `--`.
+    op: BinOp,
+    prefix: bool,
 }
 
-#[derive(Debug)]
-pub enum Value<'a> {
-    String(String),
-    Bool(bool),
-    Int(i64),
-    Float(f64),
-    Fn(FnValue<'a>),
-    Unit,
+impl UpdateExpr {
+    fn new(expr: &Node) -> Result<Self, ParseError> {
+        let op = match expr.str("operator")? {
+            "++" => BinOp::Add,
+            "--" => BinOp::Sub,
+            op => return Err(expr.error(ParseErrorKind::UnsupportedOperator(op.to_owned()))),
+        };
+
+        Ok(UpdateExpr {
+            target: Target::new(&expr.get("argument")?)?,
+            op,
+            prefix: expr.get("prefix")?.json() == &serde_json::Value::Bool(true),
+        })
+    }
 }
 
-fn eval_error(msg: &str) -> Result<Value, &str> {
-    Err(msg)
+/// The array index property `key` names, if it's an integer. Negative
+/// ones are never in bounds.
+fn index(key: &str) -> Option<i64> {
+    key.parse::<i64>().ok().filter(|i| i.to_string() == key)
 }
 
-impl Value<'_> {
-    fn from_json(expr: &serde_json::Value) -> Self {
-        let value = expr.get("value").unwrap();
-        if value.is_i64() {
-            Self::Int(value.as_i64().unwrap())
-        } else if value.is_f64() {
-            Self::Float(value.as_f64().unwrap())
-        } else if value.is_string() {
-            Self::String(String::from(value.as_str().unwrap()))
-        } else if value.is_boolean() {
-            Self::Bool(value.as_bool().unwrap())
-        } else {
-            unimplemented!("tried to create inter::ast::Value out of unsupported type")
+/// What an assignment or update writes to.
+#[derive(Debug)]
+enum Target {
+    Ident(String),
+    Member(MemberExpr),
+}
+
+impl Target {
+    fn new(expr: &Node) -> Result<Self, ParseError> {
+        match expr.node_type()? {
+            "Identifier" => Ok(Target::Ident(expr.str("name")?.to_owned())),
+            "MemberExpression" => Ok(Target::Member(MemberExpr::new(expr)?)),
+            _ => Err(expr.error(ParseErrorKind::UnsupportedNode)),
         }
     }
 
-    fn extract_bool(self) -> Result<bool, &'static str> {
-        if let Self::Bool(b) = self {
-            Ok(b == true)
-        } else {
-            Err("non bool value in bool operator")
+    fn yields(&self) -> bool {
+        match self {
+            Target::Ident(_) => false,
+            Target::Member(expr) => expr.object.yields || expr.property.yields(),
         }
     }
 }
 
-impl Display for Value<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        use Value::*;
+impl Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            Int(v) => write!(f, "(value (number {}))", v),
-            Float(v) => write!(f, "(value (number {}))", v),
-            Bool(v) => write!(f, "(value (boolean {}))", v),
-            String(v) => write!(f, "(value (string {}))", v),
-            Fn(_) => write!(f, "(value (function))"),
-            Unit => write!(f, "(value ())"),
+            Target::Ident(ident) => write!(f, "{}", ident),
+            Target::Member(expr) => write!(f, "(member {} {})", expr.object, expr.property),
         }
     }
 }
 
-// TODO or this
-impl Clone for Value<'_> {
-    fn clone(&self) -> Self {
-        use Value::*;
-        match self {
-            String(s) => String(s.clone()),
-            Bool(b) => Bool(*b),
-            Int(i) => Int(*i),
-            Float(f) => Float(*f),
-            Unit => Unit,
-            Fn(_) => unimplemented!("Literal(Value::Func)::clone()"),
-        }
-    }
-}
-
-//#[derive(Debug, Clone)]
-// struct Environ<'a> {
-//     env: HashMap<String, Value<'a>>,
-// }
-
-type Environ<'a> = HashMap<String, Value<'a>>;
-
-// impl Environ<'_> {
-//     fn empty() -> Self {
-//         Environ {
-//             env: HashMap::new(),
-//         }
-//     }
-//
-//     fn extend(&self, ident: String, val: Value) -> Self {
-//         let mut extended = self.env.clone();
-//         extended.insert(ident, val);
-//         Environ { env: extended }
-//     }
-//
-//     fn lookup(&self, ident: &String) -> Option<&Value> {
-//         self.env.get(ident)
-//     }
-// }
-
+/// A `Target` with its object and key evaluated, ready to be read and
+/// written.
 #[derive(Debug)]
-enum Expr<'a> {
-    Binary(Box<BinaryExpr<'a>>),
-    Unary(Box<UnaryExpr<'a>>),
-    Conditional(Box<CondExpr<'a>>),
-    Fn(Box<FnExpr<'a>>),
-    Bind(Box<BindExpr<'a>>),
-    Ref(String),
-    Literal(Value<'a>),
+enum Place {
+    Binding(Binding, String),
+    Property(Value, String),
 }
 
-impl Expr<'_> {
-    fn new(expr: &serde_json::Value) -> Self {
-        let expr_type = match expr.get("type") {
-            Some(t) => t.as_str().unwrap(),
-            None => unimplemented!("none expr type should be unreachable"),
-        };
+/// The left of a declaration, or a parameter: a name, or an object or
+/// array pattern taking a value apart into several names.
+#[derive(Debug)]
+enum Pattern {
+    Ident(String),
+    /// `pattern = expr`, where `expr` takes the place of an undefined value.
+    Default(Box<Pattern>, Expr),
+    /// `{ key: pattern, ...rest }`, `rest` getting the properties not
+    /// named.
+    Object {
+        props: Vec<(Key, Pattern)>,
+        rest: Option<String>,
+    },
+    /// `[pattern, , pattern, ...rest]`, `None` being a hole that skips an
+    /// element.
+    Array {
+        elems: Vec<Option<Pattern>>,
+        rest: Option<Box<Pattern>>,
+    },
+}
 
-        match expr_type {
-            "BinaryExpression" | "LogicalExpression" => {
-                Expr::Binary(Box::new(BinaryExpr::new(expr)))
-            }
-            "BlockStatement" => {
-                match Expr::from_body(expr.get("body").unwrap().as_array().unwrap()) {
-                    Some(expr) => expr,
-                    None => Expr::Literal(Value::Unit),
+impl Pattern {
+    fn new(pattern: &Node) -> Result<Self, ParseError> {
+        match pattern.node_type()? {
+            "Identifier" => Ok(Pattern::Ident(pattern.str("name")?.to_owned())),
+            "AssignmentPattern" => Ok(Pattern::Default(
+                Box::new(Pattern::new(&pattern.get("left")?)?),
+                Expr::new(&pattern.get("right")?)?,
+            )),
+            "ObjectPattern" => {
+                let mut props = Vec::new();
+                let mut rest = None;
+                let nodes = pattern.array("properties")?;
+                for (i, prop) in nodes.iter().enumerate() {
+                    match prop.node_type()? {
+                        "Property" => {
+                            let key = Key::new(&prop.get("key")?, computed(prop))?;
+                            props.push((key, Pattern::new(&prop.get("value")?)?));
+                        }
+                        "RestElement" if i + 1 == nodes.len() => {
+                            rest = Some(prop.get("argument")?.str("name")?.to_owned());
+                        }
+                        "RestElement" => {
+                            return Err(pattern.error(ParseErrorKind::Malformed("properties")))
+                        }
+                        _ => return Err(prop.error(ParseErrorKind::UnsupportedNode)),
+                    }
                 }
+                Ok(Pattern::Object { props, rest })
             }
-            // have to do this inline for lifetime reasons
-            "FunctionExpression" => {
-                let body = match Expr::from_body(expr.get("body").unwrap().as_array().unwrap()) {
-                    Some(expr) => expr,
-                    None => Expr::Literal(Value::Unit),
-                };
-                let arg = String::from(
-                    expr.get("params")
-                        .unwrap()
-                        .as_array()
-                        .unwrap()
-                        .first()
-                        .unwrap()
-                        .get("name")
-                        .unwrap()
-                        .as_str()
-                        .unwrap(),
-                );
-                Expr::Fn(Box::new(FnExpr { body, arg }))
+            "ArrayPattern" => {
+                let mut elems = Vec::new();
+                let mut rest = None;
+                let json = pattern.get("elements")?;
+                let nodes = pattern.array("elements")?;
+                for (i, elem) in nodes.iter().enumerate() {
+                    if json.json()[i].is_null() {
+                        elems.push(None);
+                        continue;
+                    }
+                    match elem.node_type()? {
+                        "RestElement" if i + 1 == nodes.len() => {
+                            rest = Some(Box::new(Pattern::new(&elem.get("argument")?)?));
+                        }
+                        "RestElement" => {
+                            return Err(pattern.error(ParseErrorKind::Malformed("elements")))
+                        }
+                        _ => elems.push(Some(Pattern::new(elem)?)),
+                    }
+                }
+                Ok(Pattern::Array { elems, rest })
             }
-            "UnaryExpression" => Expr::Unary(Box::new(UnaryExpr::new(expr))),
-            "ConditionalExpression" => Expr::Conditional(Box::new(CondExpr::new(expr))),
-            "Literal" => Expr::Literal(Value::from_json(expr)),
-            "Identifier" => Expr::Ref(expr.get("name").unwrap().as_str().unwrap().into()),
-            "ExpressionStatement" => Expr::new(expr.get("expression").unwrap()),
-            "ReturnStatement" => Expr::new(expr.get("argument").unwrap()),
-            "AssignmentExpression" => todo!("Expr::new AssignmentExpression"),
-            _ => todo!("reached end of expr type match arms: {}", expr_type),
+            _ => Err(pattern.error(ParseErrorKind::UnsupportedNode)),
         }
     }
 
-    fn from_body(body: &[serde_json::Value]) -> Option<Self> {
-        if body.len() < 1 {
-            return None;
-        }
-        let first = &body[0];
-        let statement = match first.get("type").unwrap().as_str().unwrap() {
-            "VariableDeclaration" => Expr::Bind(Box::new(BindExpr::new(first, &body[1..]))),
-            "ExpressionStatement" | "ReturnStatement" => Expr::new(first),
-            _ => todo!("unknown expression type in program::new"),
-        };
-
-        Some(statement)
+    /// Every name the pattern binds, in order.
+    fn idents(&self) -> Vec<&str> {
+        let mut idents = Vec::new();
+        self.push_idents(&mut idents);
+        idents
     }
 
-    fn eval(&self, env: &Environ) -> Result<Value, &str> {
-        use Expr::*;
-        use Value::*;
-        // I really hate this but don't really have time to find a better way
+    fn push_idents<'p>(&'p self, idents: &mut Vec<&'p str>) {
         match self {
-            Binary(expr) => {
-                use BinOp::*;
-                match expr.op {
-                    // number -> number
-                    Add | Sub | Mul | Div => {
-                        let lhs = expr.lhs.eval(env)?;
-                        let rhs = expr.rhs.eval(env)?;
-                        match (lhs, rhs) {
-                            (Int(l), Int(r)) => match expr.op {
-                                Add => Ok(Int(l + r)),
-                                Sub => Ok(Int(l - r)),
-                                Mul => Ok(Int(l * r)),
-                                Div => {
-                                    if r == 0 {
-                                        eval_error("divide by zero")
-                                    } else {
-                                        Ok(Int(l / r))
-                                    }
-                                }
-                                _ => unimplemented!("op in BinOp::Int"),
-                            },
-                            (Float(l), Float(r)) => match expr.op {
-                                Add => Ok(Float(l + r)),
-                                Sub => Ok(Float(l - r)),
-                                Mul => Ok(Float(l * r)),
-                                Div => {
-                                    if r == 0. {
-                                        eval_error("divide by zero")
-                                    } else {
-                                        Ok(Float(l / r))
-                                    }
-                                }
-                                _ => unimplemented!("op in BinOp::Int"),
-                            },
-                            _ => eval_error("non numbers in numerical binop"),
-                        }
-                    }
-
-                    // number -> boolean
-                    Lt | Le | Gt | Ge | Eq | Ne => {
-                        let lhs = expr.lhs.eval(env)?;
-                        let rhs = expr.rhs.eval(env)?;
-                        match (lhs, rhs) {
-                            (Int(l), Int(r)) => Ok(Bool(match expr.op {
-                                Lt => l < r,
-                                Le => l <= r,
-                                Gt => l > r,
-                                Ge => l >= r,
-                                Eq => l == r,
-                                Ne => l != r,
-                                _ => unimplemented!("op in BinOp::Rel::Int"),
-                            })),
-                            (Float(l), Float(r)) => Ok(Bool(match expr.op {
-                                Lt => l < r,
-                                Le => l <= r,
-                                Gt => l > r,
-                                Ge => l >= r,
-                                Eq => l == r,
-                                Ne => l != r,
-                                _ => unimplemented!("op in BinOp::Rel::Float"),
-                            })),
-                            _ => eval_error("relation bin op on non number or mixed numbers"),
-                        }
-                    }
-                    // int -> int
-                    Rem | BitXor | BitOr | BitAnd | Shl | Shr => {
-                        let lhs = expr.lhs.eval(env)?;
-                        let rhs = expr.rhs.eval(env)?;
-                        match (lhs, rhs) {
-                            (Int(l), Int(r)) => Ok(Int(match expr.op {
-                                Rem => l % r,
-                                BitXor => l ^ r,
-                                BitOr => l | r,
-                                BitAnd => l & r,
-                                Shl => l << r,
-                                Shr => l >> r,
-                                _ => unimplemented!(),
-                            })),
-                            _ => eval_error("bit op on non ints"),
-                        }
-                    }
-                    // boolean -> boolean
-                    Or => {
-                        let lhs = expr.lhs.eval(env)?;
-                        let lhs = lhs.extract_bool()?;
-                        if lhs {
-                            Ok(Bool(true))
-                        } else {
-                            let rhs = expr.rhs.eval(env)?;
-                            let rhs = rhs.extract_bool()?;
-                            if rhs {
-                                return Ok(Bool(true));
-                            }
-                            Ok(Bool(false))
-                        }
-                    }
-                    And => {
-                        let lhs = expr.lhs.eval(env)?;
-                        let lhs = lhs.extract_bool()?;
-                        if !lhs {
-                            Ok(Bool(false))
-                        } else {
-                            let rhs = expr.rhs.eval(env)?;
-                            let rhs = rhs.extract_bool()?;
-                            if rhs {
-                                return Ok(Bool(true));
-                            }
-                            Ok(Bool(false))
-                        }
-                    }
+            Pattern::Ident(ident) => idents.push(ident),
+            Pattern::Default(pattern, _) => pattern.push_idents(idents),
+            Pattern::Object { props, rest } => {
+                for (_, pattern) in props {
+                    pattern.push_idents(idents);
                 }
+                idents.extend(rest.as_deref());
             }
-            Unary(expr) => {
-                let arg = expr.expr.eval(env)?;
-
-                use UnaryOp::*;
-                match expr.op {
-                    Plus => match arg {
-                        Int(v) => Ok(Int(if v < 0 { v * -1 } else { v })),
-                        Float(v) => Ok(Float(if v < 0. { v * -1. } else { v })),
-                        _ => eval_error("unary plus non number"),
-                    },
-                    Minus => match arg {
-                        Int(v) => Ok(Int(v * -1)),
-                        Float(v) => Ok(Float(v * -1.)),
-                        _ => eval_error("unary minus non number"),
-                    },
-                    Not => match arg {
-                        Bool(v) => Ok(Bool(!v)),
-                        _ => eval_error("unary not non bool"),
-                    },
-                    BitNot => match arg {
-                        Int(v) => Ok(Int(!v)),
-                        _ => eval_error("unary bit not non int"),
-                    },
+            Pattern::Array { elems, rest } => {
+                for pattern in elems.iter().flatten() {
+                    pattern.push_idents(idents);
+                }
+                if let Some(rest) = rest {
+                    rest.push_idents(idents);
                 }
             }
-            Conditional(expr) => {
-                let test = expr.test.eval(env)?;
-                if let Bool(cond) = test {
-                    if cond {
-                        Ok(expr.cons.eval(env)?)
-                    } else {
-                        Ok(expr.altr.eval(env)?)
-                    }
-                } else {
-                    eval_error("conditional non bool test")
+        }
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Pattern::Ident(ident) => write!(f, "{}", ident),
+            Pattern::Default(pattern, default) => write!(f, "(default {} {})", pattern, default),
+            Pattern::Object { props, rest } => {
+                write!(f, "(object-pattern")?;
+                for (key, pattern) in props {
+                    write!(f, " [{} {}]", key, pattern)?;
                 }
+                if let Some(rest) = rest {
+                    write!(f, " (rest {})", rest)?;
+                }
+                write!(f, ")")
             }
-            Expr::Fn(expr) => Ok(Value::Fn(FnValue {
-                arg: expr.arg.clone(),
-                body: Box::new(Box::leak(Box::new(&expr.body))),
-                env: Box::leak(Box::new(env))
-            })),
-            Bind(expr) => {
-                let mut new_env = env.clone();
-                for (ident, bind_expr) in &expr.binds {
-                    let bound_val = bind_expr.eval(&new_env)?;
-                    new_env.insert(ident.clone(), bound_val);
-                    //new_env = new_env.extend(ident.clone(), bound_val);
+            Pattern::Array { elems, rest } => {
+                write!(f, "(array-pattern")?;
+                for elem in elems {
+                    match elem {
+                        Some(pattern) => write!(f, " {}", pattern)?,
+                        None => write!(f, " ()")?,
+                    }
+                }
+                if let Some(rest) = rest {
+                    write!(f, " (rest {})", rest)?;
                 }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Param {
+    pattern: Pattern,
+    /// Evaluated in place of a missing argument.
+    default: Option<Expr>,
+}
+
+#[derive(Debug)]
+struct FnExpr {
+    params: Vec<Param>,
+    /// The `...rest` parameter, bound to an array of the arguments left over.
+    rest: Option<Pattern>,
+    body: Expr,
+    /// Whether this is a `function*`, whose calls give a generator running
+    /// `body` a `yield` at a time.
+    generator: bool,
+}
+
+impl FnExpr {
+    fn new(expr: &Node) -> Result<Self, ParseError> {
+        let mut params = Vec::new();
+        let mut rest = None;
 
-                expr.body.eval(&new_env)
+        let nodes = expr.array("params")?;
+        for (i, param) in nodes.iter().enumerate() {
+            match param.node_type()? {
+                "AssignmentPattern" => params.push(Param {
+                    pattern: Pattern::new(&param.get("left")?)?,
+                    default: Some(Expr::new(&param.get("right")?)?),
+                }),
+                "RestElement" if i + 1 == nodes.len() => {
+                    rest = Some(Pattern::new(&param.get("argument")?)?);
+                }
+                "RestElement" => return Err(expr.error(ParseErrorKind::Malformed("params"))),
+                _ => params.push(Param {
+                    pattern: Pattern::new(param)?,
+                    default: None,
+                }),
             }
-            // Ref(ident) => match env.get(ident) {
-            //     Some(val) => Ok(val.clone()),
-            //     None => eval_error("unbound identifier"),
-            // },
-            Literal(val) => Ok(match val {
-                Int(v) => Int(*v),
-                Float(v) => Float(*v),
-                Bool(v) => Bool(*v),
-                String(s) => String(s.clone()),
-                Unit => Unit,
-                Value::Fn(_) => unimplemented!("Expr::Eval::Literal(Value::Func)"),
-            }),
-            _ => panic!("reached end of Expr::eval match arms"),
         }
+
+        // an arrow function's body can be a lone expression
+        let body = expr.get("body")?;
+        let body = match body.node_type()? {
+            "BlockStatement" => match Expr::from_body(&body.array("body")?)? {
+                Some(expr) => expr,
+                None => Expr::unit(expr.span()),
+            },
+            _ => Expr::new(&body)?,
+        };
+
+        Ok(FnExpr {
+            params,
+            rest,
+            body,
+            generator: expr.json().get("generator") == Some(&serde_json::Value::Bool(true)),
+        })
+    }
+
+    /// How many arguments a call needs: the parameters up to the first with
+    /// a default.
+    fn required(&self) -> usize {
+        self.params
+            .iter()
+            .take_while(|param| param.default.is_none())
+            .count()
+    }
+}
+
+#[derive(Debug)]
+struct CallExpr {
+    callee: Expr,
+    args: Vec<Expr>,
+}
+
+impl CallExpr {
+    fn new(expr: &Node) -> Result<Self, ParseError> {
+        let args = expr
+            .array("arguments")?
+            .iter()
+            .map(Expr::new)
+            .collect::<Result<_, _>>()?;
+
+        Ok(CallExpr {
+            callee: Expr::new(&expr.get("callee")?)?,
+            args,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct MemberExpr {
+    object: Expr,
+    property: Key,
+}
+
+impl MemberExpr {
+    fn new(expr: &Node) -> Result<Self, ParseError> {
+        Ok(MemberExpr {
+            object: Expr::new(&expr.get("object")?)?,
+            property: Key::new(&expr.get("property")?, computed(expr))?,
+        })
+    }
+}
+
+/// The name of a property in a member expression or object literal.
+#[derive(Debug)]
+enum Key {
+    /// `o.name`, `{ name: ... }`, `{ "name": ... }` or `{ 1: ... }`.
+    Named(String),
+    /// `o[expr]` or `{ [expr]: ... }`.
+    Computed(Expr),
+}
+
+impl Key {
+    fn new(key: &Node, computed: bool) -> Result<Self, ParseError> {
+        if computed {
+            return Ok(Key::Computed(Expr::new(key)?));
+        }
+        match key.node_type()? {
+            "Identifier" => Ok(Key::Named(key.str("name")?.to_owned())),
+            "Literal" => match Value::from_json(key)? {
+                Value::String(name) => Ok(Key::Named(name)),
+                val @ (Value::Int(_) | Value::Float(_)) => Ok(Key::Named(js::to_string(&val))),
+                _ => Err(key.error(ParseErrorKind::UnsupportedLiteral)),
+            },
+            _ => Err(key.error(ParseErrorKind::UnsupportedNode)),
+        }
+    }
+
+    fn yields(&self) -> bool {
+        matches!(self, Key::Computed(expr) if expr.yields)
     }
 }
 
-impl Display for Expr<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        use Expr::*;
+impl Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            Binary(expr) => {
-                use BinOp::*;
-                let op_type = match expr.op {
-                    Add | Sub | Mul | Div | Rem | BitOr | BitAnd | BitXor | Shl | Shr => {
-                        "arithmetic"
-                    }
-                    Or | And => "logical",
-                    Eq | Ne | Lt | Le | Gt | Ge => "relational",
-                };
-                write!(f, "({} {} {} {})", op_type, expr.op, expr.lhs, expr.rhs)
-            }
-            Unary(expr) => {
-                write!(f, "(unary {} {})", expr.op, expr.expr)
-            }
-            Conditional(expr) => {
-                write!(f, "(conditional {} {} {})", expr.test, expr.cons, expr.altr)
-            }
-            Fn(expr) => {
-                write!(f, "(fn ({}) {})", expr.arg, expr.body)
-            }
-            Literal(val) => {
-                write!(f, "{}", val)
+            Key::Named(name) => write!(f, "{}", name),
+            Key::Computed(expr) => write!(f, "(computed {})", expr),
+        }
+    }
+}
+
+/// Whether a member expression or property has its key in brackets.
+fn computed(expr: &Node) -> bool {
+    expr.json().get("computed") == Some(&serde_json::Value::Bool(true))
+}
+
+/// An array literal, holes like `[1, , 3]` being undefined.
+#[derive(Debug)]
+struct ArrayExpr {
+    elems: Vec<Expr>,
+}
+
+impl ArrayExpr {
+    fn new(expr: &Node) -> Result<Self, ParseError> {
+        let elems = expr
+            .get("elements")?
+            .json()
+            .as_array()
+            .ok_or_else(|| expr.error(ParseErrorKind::Malformed("elements")))?;
+        let mut nodes = expr.array("elements")?.into_iter();
+
+        let mut result = Vec::new();
+        for elem in elems {
+            let node = nodes.next().expect("one node per element");
+            result.push(match elem {
+                serde_json::Value::Null => Expr::unit(None),
+                _ if node.node_type()? == "SpreadElement" => {
+                    return Err(node.error(ParseErrorKind::UnsupportedNode))
+                }
+                _ => Expr::new(&node)?,
+            });
+        }
+        Ok(ArrayExpr { elems: result })
+    }
+}
+
+/// `try`, with a `catch`, a `finally` or both.
+#[derive(Debug)]
+struct TryExpr {
+    block: Expr,
+    /// The `catch` clause, with what it binds the thrown value to, if
+    /// anything.
+    handler: Option<(Option<Pattern>, Expr)>,
+    finalizer: Option<Expr>,
+}
+
+impl TryExpr {
+    fn new(expr: &Node) -> Result<Self, ParseError> {
+        let handler = match expr.get_opt("handler") {
+            Some(handler) => {
+                let param = handler
+                    .get_opt("param")
+                    .as_ref()
+                    .map(Pattern::new)
+                    .transpose()?;
+                Some((param, Expr::new(&handler.get("body")?)?))
             }
-            Ref(ident) => {
-                write!(f, "{}", ident)
+            None => None,
+        };
+        let finalizer = expr
+            .get_opt("finalizer")
+            .as_ref()
+            .map(Expr::new)
+            .transpose()?;
+        if handler.is_none() && finalizer.is_none() {
+            return Err(expr.error(ParseErrorKind::Missing("handler")));
+        }
+
+        Ok(TryExpr {
+            block: Expr::new(&expr.get("block")?)?,
+            handler,
+            finalizer,
+        })
+    }
+}
+
+/// The built-in methods of arrays.
+#[derive(Debug, Clone, Copy)]
+enum ArrayMethod {
+    Map,
+    Filter,
+    Reduce,
+    ForEach,
+}
+
+impl ArrayMethod {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "map" => ArrayMethod::Map,
+            "filter" => ArrayMethod::Filter,
+            "reduce" => ArrayMethod::Reduce,
+            "forEach" => ArrayMethod::ForEach,
+            _ => return None,
+        })
+    }
+}
+
+impl Display for ArrayMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ArrayMethod::Map => write!(f, "map"),
+            ArrayMethod::Filter => write!(f, "filter"),
+            ArrayMethod::Reduce => write!(f, "reduce"),
+            ArrayMethod::ForEach => write!(f, "forEach"),
+        }
+    }
+}
+
+/// An object literal, its properties in the order they are written.
+#[derive(Debug)]
+struct ObjectExpr {
+    props: Vec<(Key, Expr)>,
+}
+
+impl ObjectExpr {
+    fn new(expr: &Node) -> Result<Self, ParseError> {
+        let mut props = Vec::new();
+        for prop in expr.array("properties")? {
+            // spreads and methods, getters and setters aren't supported
+            if prop.node_type()? != "Property" || prop.str("kind")? != "init" {
+                return Err(prop.error(ParseErrorKind::UnsupportedNode));
             }
-            Bind(expr) => {
-                write!(f, "(let ").expect("unable to print bind");
-                expr.binds.iter().for_each(|expr| {
-                    write!(f, "{} = {}, ", expr.0, expr.1).expect("unable to print bind 2");
-                });
-                write!(f, ")")
+            if prop.json().get("method") == Some(&serde_json::Value::Bool(true)) {
+                return Err(prop.error(ParseErrorKind::UnsupportedNode));
             }
+            let key = Key::new(&prop.get("key")?, computed(&prop))?;
+            props.push((key, Expr::new(&prop.get("value")?)?));
         }
+        Ok(ObjectExpr { props })
     }
 }
 
+/// A template literal: `quasis` are the text around each of `exprs`, so
+/// there is always one more of them.
 #[derive(Debug)]
-pub struct Program<'a> {
-    statement: Expr<'a>,
-}
-
-impl Program<'_> {
-    // pub fn new(body: &serde_json::Value) -> Self {
-    //     let body = body.as_array().unwrap();
-    //
-    //     let statement = match Expr::from_body(body) {
-    //         Some(statement) => statement,
-    //         None => Expr::Literal(Value::Unit),
-    //     };
-    //
-    //     Program { statement }
-    // }
-    //
-    // pub fn run(&self) {
-    //     let env = Environ::empty();
-    //     match self.statement.eval(&env) {
-    //         Ok(val) => println!("{}", val),
-    //         Err(err) => println!("(error \"{} banana\")", err),
-    //     };
-    // }
-
-    pub fn parse_and_run(body: &serde_json::Value) {
-        let body = body.as_array().unwrap();
-        let statement = match Expr::from_body(body) {
-            Some(statement) => statement,
-            None => Expr::Literal(Value::Unit),
-        };
+struct TemplateExpr {
+    quasis: Vec<String>,
+    exprs: Vec<Expr>,
+}
+
+impl TemplateExpr {
+    fn new(expr: &Node) -> Result<Self, ParseError> {
+        let quasis = expr
+            .array("quasis")?
+            .iter()
+            .map(|quasi| Ok(quasi.get("value")?.str("cooked")?.to_owned()))
+            .collect::<Result<Vec<_>, ParseError>>()?;
+        let exprs = expr
+            .array("expressions")?
+            .iter()
+            .map(Expr::new)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if quasis.len() != exprs.len() + 1 {
+            return Err(expr.error(ParseErrorKind::Malformed("quasis")));
+        }
+        Ok(TemplateExpr { quasis, exprs })
+    }
+
+    /// The string with `vals`, the values of `exprs`, filled in.
+    fn fill(&self, vals: &[Value]) -> String {
+        // interpolation converts to a string whatever the semantics
+        let mut result = self.quasis[0].clone();
+        for (val, quasi) in vals.iter().zip(&self.quasis[1..]) {
+            result += &js::to_string(val);
+            result += quasi;
+        }
+        result
+    }
+}
 
-        let env = Environ::new();
-        match statement.eval(&env) {
-            Ok(val) => println!("{}", val),
-            Err(err) => println!("(error \"{} banana\")", err),
+/// `yield`, or `yield*` when `delegate`, handing each value of another
+/// generator or array on in turn.
+#[derive(Debug)]
+struct YieldExpr {
+    arg: Option<Expr>,
+    delegate: bool,
+}
+
+impl YieldExpr {
+    fn new(expr: &Node) -> Result<Self, ParseError> {
+        let delegate = expr.json().get("delegate") == Some(&serde_json::Value::Bool(true));
+        let arg = expr
+            .get_opt("argument")
+            .as_ref()
+            .map(Expr::new)
+            .transpose()?;
+        if delegate && arg.is_none() {
+            return Err(expr.error(ParseErrorKind::Missing("argument")));
+        }
+        Ok(YieldExpr { arg, delegate })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum LoopKind {
+    While,
+    DoWhile,
+    For,
+}
+
+impl Display for LoopKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LoopKind::While => write!(f, "while"),
+            LoopKind::DoWhile => write!(f, "do-while"),
+            LoopKind::For => write!(f, "for"),
+        }
+    }
+}
+
+/// Any of the loops, as a `for` loop: `while` only has a test, and
+/// `do ... while` runs its body once before checking it.
+#[derive(Debug)]
+struct LoopExpr {
+    kind: LoopKind,
+    /// The names `break` and `continue` can refer to this loop by.
+    labels: Vec<String>,
+    /// Declarations in the `for` initializer, in scope for the whole loop.
+    decls: Vec<Decl>,
+    /// Whether `decls` are bound afresh for each iteration, as `let` and
+    /// `const` are, so closures made in the body see that iteration's
+    /// values. A `var` is one binding for the whole loop.
+    per_iteration: bool,
+    /// An expression `for` initializer.
+    init: Option<Expr>,
+    test: Option<Expr>,
+    update: Option<Expr>,
+    body: Expr,
+}
+
+impl LoopExpr {
+    fn new(expr: &Node, labels: Vec<String>) -> Result<Self, ParseError> {
+        let kind = match expr.node_type()? {
+            "WhileStatement" => LoopKind::While,
+            "DoWhileStatement" => LoopKind::DoWhile,
+            "ForStatement" => LoopKind::For,
+            _ => return Err(expr.error(ParseErrorKind::UnsupportedNode)),
         };
+
+        let mut decls = Vec::new();
+        let mut per_iteration = false;
+        let mut init = None;
+        if let Some(node) = expr.get_opt("init") {
+            match node.node_type()? {
+                "VariableDeclaration" => {
+                    decls = Decl::from_declaration(&node)?;
+                    per_iteration = node.str("kind")? != "var";
+                }
+                _ => init = Some(Expr::new(&node)?),
+            }
+        }
+
+        let opt = |key| expr.get_opt(key).as_ref().map(Expr::new).transpose();
+        Ok(LoopExpr {
+            kind,
+            labels,
+            decls,
+            per_iteration,
+            init,
+            test: opt("test")?,
+            update: opt("update")?,
+            body: Expr::new(&expr.get("body")?)?,
+        })
+    }
+
+    /// The names bound afresh for each iteration.
+    fn fresh_idents(&self) -> Vec<&str> {
+        match self.per_iteration {
+            true => self
+                .decls
+                .iter()
+                .flat_map(|decl| decl.pattern.idents())
+                .collect(),
+            false => Vec::new(),
+        }
     }
 }
 
-impl Display for Program<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.statement)
+/// Whether a `break` or `continue` to `label` means the loop with `labels`.
+fn targets(labels: &[String], label: &Option<String>) -> bool {
+    match label {
+        Some(label) => labels.contains(label),
+        None => true,
+    }
+}
+
+/// `for (left of right) body`, running `body` for each value of an array
+/// or generator.
+#[derive(Debug)]
+struct ForOfExpr {
+    labels: Vec<String>,
+    left: ForOfLeft,
+    right: Expr,
+    body: Expr,
+}
+
+/// What each value of a `for...of` goes to.
+#[derive(Debug)]
+enum ForOfLeft {
+    /// A declared pattern, bound afresh for each value unless it's a `var`,
+    /// as in a `for` loop.
+    Decl {
+        pattern: Pattern,
+        constant: bool,
+        per_iteration: bool,
+    },
+    /// A name or property already in scope, assigned each value.
+    Target(Target),
+}
+
+impl ForOfExpr {
+    fn new(expr: &Node, labels: Vec<String>) -> Result<Self, ParseError> {
+        let left = expr.get("left")?;
+        let left = match left.node_type()? {
+            "VariableDeclaration" => {
+                let decls = left.array("declarations")?;
+                let [decl] = decls.as_slice() else {
+                    return Err(left.error(ParseErrorKind::Malformed("declarations")));
+                };
+                let kind = left.str("kind")?;
+                ForOfLeft::Decl {
+                    pattern: Pattern::new(&decl.get("id")?)?,
+                    constant: kind == "const",
+                    per_iteration: kind != "var",
+                }
+            }
+            _ => ForOfLeft::Target(Target::new(&left)?),
+        };
+
+        Ok(ForOfExpr {
+            labels,
+            left,
+            right: Expr::new(&expr.get("right")?)?,
+            body: Expr::new(&expr.get("body")?)?,
+        })
+    }
+}
+
+impl Display for ForOfLeft {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ForOfLeft::Decl {
+                pattern,
+                constant: true,
+                ..
+            } => write!(f, "(const {})", pattern),
+            ForOfLeft::Decl {
+                pattern,
+                per_iteration: true,
+                ..
+            } => write!(f, "(let {})", pattern),
+            ForOfLeft::Decl { pattern, .. } => write!(f, "(var {})", pattern),
+            ForOfLeft::Target(target) => write!(f, "{}", target),
+        }
+    }
+}
+
+/// A closure: the function expression it was created from, and the
+/// environment it captured at creation.
+#[derive(Debug, Clone)]
+pub struct FnValue {
+    env: Environ,
+    func: Rc<FnExpr>,
+    /// Arguments already applied to a curried function.
+    args: Rc<[Value]>,
+}
+
+impl FnValue {
+    /// Whether `self` and `other` are the same closure.
+    pub(crate) fn is(&self, other: &FnValue) -> bool {
+        // each closure and partial application gets its own `args`
+        Rc::ptr_eq(&self.func, &other.func)
+            && self.env.is(&other.env)
+            && Rc::ptr_eq(&self.args, &other.args)
+    }
+
+    /// Call `self` with as many of `args` as it has parameters left for, as
+    /// a callback that may ignore some of what it's given.
+    fn call_back(&self, mut args: Vec<Value>, cx: &Interp) -> Result<Value, Unwind> {
+        if self.func.rest.is_none() {
+            args.truncate(self.func.params.len().saturating_sub(self.args.len()));
+        }
+        self.call(args, cx)
+    }
+
+    fn call(&self, args: Vec<Value>, cx: &Interp) -> Result<Value, Unwind> {
+        let func = &self.func;
+        let mut args: Vec<_> = self.args.iter().cloned().chain(args).collect();
+
+        let required = func.required();
+        if cx.opts.curry && args.len() < required {
+            return Ok(Value::Fn(FnValue {
+                args: args.into(),
+                ..self.clone()
+            }));
+        }
+        let too_many = func.rest.is_none() && args.len() > func.params.len();
+        if !cx.opts.curry
+            && cx.opts.semantics == Semantics::Strict
+            && (args.len() < required || too_many)
+        {
+            return Err(EvalErrorKind::Arity {
+                min: required,
+                max: func.rest.is_none().then_some(func.params.len()),
+                found: args.len(),
+            }
+            .into());
+        }
+        // a curried function passes what it doesn't take on to its result
+        let extra = if cx.opts.curry && too_many {
+            args.split_off(func.params.len())
+        } else {
+            Vec::new()
+        };
+
+        let mut args = args.into_iter();
+        let mut call_env = self.env.clone();
+        for param in &func.params {
+            let arg = match (args.next(), &param.default) {
+                (None | Some(Value::Unit), Some(default)) => default.eval(&call_env, cx)?,
+                (Some(arg), _) => arg,
+                (None, None) => Value::Unit,
+            };
+            call_env = cx.bind(&call_env, &param.pattern, arg)?;
+        }
+        if let Some(rest) = &func.rest {
+            let rest_val = Value::Array(Rc::new(RefCell::new(args.collect())));
+            call_env = cx.bind(&call_env, rest, rest_val)?;
+        }
+
+        // a generator's body waits for its first `next`
+        let result = if func.generator {
+            let gen = Generator::new(Rc::clone(func), call_env);
+            Value::Generator(Rc::new(RefCell::new(gen)))
+        } else {
+            let result = cx.nested(|| func.body.eval(&call_env, cx));
+            cx.release(call_env, &self.env);
+            match result {
+                Ok(result) | Err(Unwind::Return(result)) => result,
+                Err(unwind) => return Err(unwind.stray()),
+            }
+        };
+        match result {
+            _ if extra.is_empty() => Ok(result),
+            Value::Fn(result) => result.call(extra, cx),
+            callee => Err(EvalErrorKind::NotCallable { callee }.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    String(String),
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Fn(FnValue),
+    /// Shared by every copy, as javascript arrays are.
+    Array(Rc<RefCell<Vec<Value>>>),
+    /// Shared by every copy, as javascript objects are.
+    Object(Rc<RefCell<ObjectValue>>),
+    /// The suspended call of a `function*`, shared by every copy.
+    Generator(Rc<RefCell<Generator>>),
+    /// `null`.
+    Null,
+    /// `undefined`, and the value of anything with no value of its own.
+    Unit,
+}
+
+/// The properties of an object, in the order they were added.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectValue {
+    props: Vec<(String, Value)>,
+}
+
+impl ObjectValue {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.props
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, val)| val)
+    }
+
+    /// Give the property `key` the value `val`, adding it if it's new.
+    pub fn set(&mut self, key: String, val: Value) {
+        match self.props.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, old)) => *old = val,
+            None => self.props.push((key, val)),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.props.iter().map(|(key, val)| (key.as_str(), val))
+    }
+}
+
+fn type_mismatch(op: &impl Display, operands: Vec<Value>) -> Result<Value, EvalError> {
+    Err(EvalErrorKind::TypeMismatch {
+        op: op.to_string(),
+        operands,
+    }
+    .into())
+}
+
+impl Value {
+    fn from_json(expr: &Node) -> Result<Self, ParseError> {
+        use serde_json::Value as Json;
+        match expr.json().get("value") {
+            Some(Json::Number(n)) => match n.as_i64() {
+                Some(i) => Ok(Self::Int(i)),
+                None => n
+                    .as_f64()
+                    .map(Self::Float)
+                    .ok_or_else(|| expr.error(ParseErrorKind::UnsupportedLiteral)),
+            },
+            Some(Json::String(s)) => Ok(Self::String(s.clone())),
+            Some(Json::Bool(b)) => Ok(Self::Bool(*b)),
+            Some(Json::Null) if expr.str("raw")? == "null" => Ok(Self::Null),
+            Some(_) => Err(expr.error(ParseErrorKind::UnsupportedLiteral)),
+            None => Err(expr.error(ParseErrorKind::Missing("value"))),
+        }
+    }
+
+    /// The value as plain json, functions becoming `{"type": "function"}`,
+    /// generators `{"type": "generator"}` and an object or array inside
+    /// itself `{"type": "circular"}`.
+    pub fn to_json(&self) -> serde_json::Value {
+        self.to_json_in(&mut Vec::new())
+    }
+
+    /// `to_json`, inside the objects and arrays in `outer`.
+    fn to_json_in(&self, outer: &mut Vec<*const ()>) -> serde_json::Value {
+        use serde_json::json;
+        use Value::*;
+        match self {
+            Int(v) => json!(v),
+            // as javascript prints it, `1` rather than `1.0`
+            Float(v) if v.fract() == 0. && v.abs() < 2f64.powi(53) => json!(*v as i64),
+            Float(v) => json!(v),
+            Bool(v) => json!(v),
+            String(v) => json!(v),
+            Fn(_) => json!({ "type": "function" }),
+            Generator(_) => json!({ "type": "generator" }),
+            Array(elems) => {
+                let ptr = Rc::as_ptr(elems).cast();
+                if outer.contains(&ptr) {
+                    return json!({ "type": "circular" });
+                }
+                outer.push(ptr);
+                let elems = elems
+                    .borrow()
+                    .iter()
+                    .map(|elem| elem.to_json_in(outer))
+                    .collect();
+                outer.pop();
+                elems
+            }
+            Object(obj) => {
+                let ptr = Rc::as_ptr(obj).cast();
+                if outer.contains(&ptr) {
+                    return json!({ "type": "circular" });
+                }
+                outer.push(ptr);
+                let props = obj
+                    .borrow()
+                    .iter()
+                    .map(|(key, val)| (key.to_owned(), val.to_json_in(outer)))
+                    .collect();
+                outer.pop();
+                serde_json::Value::Object(props)
+            }
+            Null | Unit => serde_json::Value::Null,
+        }
+    }
+
+    /// `Display`, inside the objects and arrays in `outer`, which one
+    /// inside itself is printed as `(circular)` instead of going around
+    /// forever.
+    fn fmt_in(&self, f: &mut std::fmt::Formatter, outer: &mut Vec<*const ()>) -> std::fmt::Result {
+        use Value::*;
+        match self {
+            Int(v) => write!(f, "(value (number {}))", v),
+            Float(v) => write!(f, "(value (number {}))", num::fmt_f64(*v)),
+            Bool(v) => write!(f, "(value (boolean {}))", v),
+            String(v) => write!(f, "(value (string {}))", quote(v)),
+            Fn(_) => write!(f, "(value (function))"),
+            Generator(_) => write!(f, "(value (generator))"),
+            Array(elems) => {
+                let ptr = Rc::as_ptr(elems).cast();
+                if outer.contains(&ptr) {
+                    return write!(f, "(circular)");
+                }
+                outer.push(ptr);
+                write!(f, "(value (array")?;
+                for elem in elems.borrow().iter() {
+                    write!(f, " ")?;
+                    elem.fmt_in(f, outer)?;
+                }
+                outer.pop();
+                write!(f, "))")
+            }
+            Object(obj) => {
+                let ptr = Rc::as_ptr(obj).cast();
+                if outer.contains(&ptr) {
+                    return write!(f, "(circular)");
+                }
+                outer.push(ptr);
+                write!(f, "(value (object")?;
+                for (key, val) in obj.borrow().iter() {
+                    write!(f, " [{} ", key)?;
+                    val.fmt_in(f, outer)?;
+                    write!(f, "]")?;
+                }
+                outer.pop();
+                write!(f, "))")
+            }
+            Null => write!(f, "(value null)"),
+            Unit => write!(f, "(value ())"),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.fmt_in(f, &mut Vec::new())
+    }
+}
+
+/// `s` as an s-expression string literal, quoted and escaped so it can be
+/// read back.
+///
+/// This is the escaping `Value`'s `Display` gives strings, offered for
+/// printing other s-expressions alongside values in the same format, as
+/// the `inter` binary does for `(error ...)`.
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[derive(Debug)]
+struct Expr {
+    kind: ExprKind,
+    /// Where in the source the expression came from, if the ESTree input
+    /// says.
+    span: Option<Span>,
+    /// Whether a `yield` of the enclosing generator is somewhere inside, so
+    /// evaluating it may suspend.
+    yields: bool,
+}
+
+#[derive(Debug)]
+enum ExprKind {
+    Binary(Box<BinaryExpr>),
+    Unary(Box<UnaryExpr>),
+    Conditional(Box<CondExpr>),
+    Fn(Rc<FnExpr>),
+    Bind(Box<BindExpr>),
+    Call(Box<CallExpr>),
+    Assign(Box<AssignExpr>),
+    Update(Box<UpdateExpr>),
+    Member(Box<MemberExpr>),
+    Object(Box<ObjectExpr>),
+    Array(Box<ArrayExpr>),
+    Template(Box<TemplateExpr>),
+    /// Statements run in order, giving the value of the last.
+    Seq(Vec<Expr>),
+    Return(Box<Expr>),
+    Loop(Box<LoopExpr>),
+    /// A statement other than a loop with a label, which `break` can leave.
+    Labeled(String, Box<Expr>),
+    Break(Option<String>),
+    Continue(Option<String>),
+    Throw(Box<Expr>),
+    Try(Box<TryExpr>),
+    Yield(Box<YieldExpr>),
+    ForOf(Box<ForOfExpr>),
+    Ref(String),
+    Literal(Value),
+}
+
+impl ExprKind {
+    /// Whether one of the expressions directly inside has a `yield` in it.
+    /// A function's `yield`s are its own, and none of the function
+    /// expression's.
+    fn yields(&self) -> bool {
+        use ExprKind::*;
+        let any = |exprs: &[Expr]| exprs.iter().any(|expr| expr.yields);
+        match self {
+            Binary(expr) => expr.lhs.yields || expr.rhs.yields,
+            Unary(expr) => expr.expr.yields,
+            Conditional(expr) => expr.test.yields || expr.cons.yields || expr.altr.yields,
+            Bind(expr) => expr.decls.iter().any(|decl| decl.init.yields) || expr.body.yields,
+            Call(expr) => expr.callee.yields || any(&expr.args),
+            Assign(expr) => expr.target.yields() || expr.value.yields,
+            Update(expr) => expr.target.yields(),
+            Member(expr) => expr.object.yields || expr.property.yields(),
+            Object(expr) => expr
+                .props
+                .iter()
+                .any(|(key, val)| key.yields() || val.yields),
+            Array(expr) => any(&expr.elems),
+            Template(expr) => any(&expr.exprs),
+            Seq(stmts) => any(stmts),
+            Return(expr) | Throw(expr) | Labeled(_, expr) => expr.yields,
+            Loop(expr) => {
+                expr.decls.iter().any(|decl| decl.init.yields)
+                    || [&expr.init, &expr.test, &expr.update]
+                        .into_iter()
+                        .any(|part| part.as_ref().is_some_and(|part| part.yields))
+                    || expr.body.yields
+            }
+            ForOf(expr) => {
+                matches!(&expr.left, ForOfLeft::Target(target) if target.yields())
+                    || expr.right.yields
+                    || expr.body.yields
+            }
+            Try(expr) => {
+                expr.block.yields
+                    || expr
+                        .handler
+                        .as_ref()
+                        .is_some_and(|(_, handler)| handler.yields)
+                    || expr
+                        .finalizer
+                        .as_ref()
+                        .is_some_and(|finalizer| finalizer.yields)
+            }
+            Yield(_) => true,
+            Fn(_) | Break(_) | Continue(_) | Ref(_) | Literal(_) => false,
+        }
+    }
+}
+
+impl Expr {
+    fn new(expr: &Node) -> Result<Self, ParseError> {
+        grow_stack(|| Expr::new_kind(expr))
+    }
+
+    fn new_kind(expr: &Node) -> Result<Self, ParseError> {
+        use ExprKind::*;
+        let kind = match expr.node_type()? {
+            "BinaryExpression" | "LogicalExpression" => Binary(Box::new(BinaryExpr::new(expr)?)),
+            "BlockStatement" => {
+                return match Expr::from_body(&expr.array("body")?)? {
+                    Some(body) => Ok(body),
+                    None => Ok(Expr::unit(expr.span())),
+                }
+            }
+            "FunctionExpression" | "ArrowFunctionExpression" => Fn(Rc::new(FnExpr::new(expr)?)),
+            "UnaryExpression" => Unary(Box::new(UnaryExpr::new(expr)?)),
+            "ConditionalExpression" | "IfStatement" => Conditional(Box::new(CondExpr::new(expr)?)),
+            "Literal" => Literal(Value::from_json(expr)?),
+            "Identifier" => Ref(expr.str("name")?.to_owned()),
+            "CallExpression" => Call(Box::new(CallExpr::new(expr)?)),
+            "AssignmentExpression" => Assign(Box::new(AssignExpr::new(expr)?)),
+            "UpdateExpression" => Update(Box::new(UpdateExpr::new(expr)?)),
+            "MemberExpression" => Member(Box::new(MemberExpr::new(expr)?)),
+            "ObjectExpression" => Object(Box::new(ObjectExpr::new(expr)?)),
+            "ArrayExpression" => Array(Box::new(ArrayExpr::new(expr)?)),
+            "TemplateLiteral" => Template(Box::new(TemplateExpr::new(expr)?)),
+            "ExpressionStatement" => return Expr::new(&expr.get("expression")?),
+            "ReturnStatement" => Return(Box::new(match expr.get_opt("argument") {
+                Some(arg) => Expr::new(&arg)?,
+                None => Expr::unit(expr.span()),
+            })),
+            "WhileStatement" | "DoWhileStatement" | "ForStatement" | "ForOfStatement" => {
+                Expr::looping(expr, Vec::new())?
+            }
+            "LabeledStatement" => return Expr::labeled(expr, Vec::new()),
+            "BreakStatement" => Break(Expr::label(expr)?),
+            "ContinueStatement" => Continue(Expr::label(expr)?),
+            "ThrowStatement" => Throw(Box::new(Expr::new(&expr.get("argument")?)?)),
+            "TryStatement" => Try(Box::new(TryExpr::new(expr)?)),
+            "YieldExpression" => Yield(Box::new(YieldExpr::new(expr)?)),
+            _ => return Err(expr.error(ParseErrorKind::UnsupportedNode)),
+        };
+
+        Ok(Expr::from_kind(kind, expr.span()))
+    }
+
+    fn from_kind(kind: ExprKind, span: Option<Span>) -> Self {
+        Expr {
+            yields: kind.yields(),
+            kind,
+            span,
+        }
+    }
+
+    /// A `while`, `do ... while`, `for` or `for...of` loop, with `labels`.
+    fn looping(expr: &Node, labels: Vec<String>) -> Result<ExprKind, ParseError> {
+        Ok(match expr.node_type()? {
+            "ForOfStatement" => ExprKind::ForOf(Box::new(ForOfExpr::new(expr, labels)?)),
+            _ => ExprKind::Loop(Box::new(LoopExpr::new(expr, labels)?)),
+        })
+    }
+
+    /// A `LabeledStatement`, inside the labels `outer`. Every label directly
+    /// on a loop names it, as in `a: b: while (...)`.
+    fn labeled(expr: &Node, mut outer: Vec<String>) -> Result<Self, ParseError> {
+        let label = expr.get("label")?.str("name")?.to_owned();
+        let body = expr.get("body")?;
+        let kind = match body.node_type()? {
+            "LabeledStatement" => {
+                outer.push(label.clone());
+                let body = Expr::labeled(&body, outer)?;
+                // the loop at the bottom already answers to `label`
+                if let ExprKind::Loop(_) | ExprKind::ForOf(_) = body.kind {
+                    return Ok(body);
+                }
+                ExprKind::Labeled(label, Box::new(body))
+            }
+            "WhileStatement" | "DoWhileStatement" | "ForStatement" | "ForOfStatement" => {
+                outer.push(label);
+                Expr::looping(&body, outer)?
+            }
+            _ => ExprKind::Labeled(label, Box::new(Expr::new(&body)?)),
+        };
+
+        Ok(Expr::from_kind(kind, expr.span()))
+    }
+
+    /// The label of a `break` or `continue`, if it has one.
+    fn label(expr: &Node) -> Result<Option<String>, ParseError> {
+        expr.get_opt("label")
+            .map(|label| Ok(label.str("name")?.to_owned()))
+            .transpose()
+    }
+
+    /// An expression evaluating to `Value::Unit`, standing in for an empty
+    /// body.
+    fn unit(span: Option<Span>) -> Self {
+        Expr::from_kind(ExprKind::Literal(Value::Unit), span)
+    }
+
+    /// The statements of a block or program, in sequence. A run of
+    /// declarations scopes over the rest of the statements after it.
+    fn from_body(body: &[Node]) -> Result<Option<Self>, ParseError> {
+        let mut stmts = Vec::new();
+
+        for (i, stmt) in body.iter().enumerate() {
+            match stmt.node_type()? {
+                "VariableDeclaration" => {
+                    let decls = body[i..]
+                        .iter()
+                        .take_while(|stmt| stmt.node_type().ok() == Some("VariableDeclaration"))
+                        .count();
+                    let (decls, rest) = body[i..].split_at(decls);
+                    stmts.push(Expr::from_kind(
+                        ExprKind::Bind(Box::new(BindExpr::new(decls, rest)?)),
+                        stmt.span(),
+                    ));
+                    break;
+                }
+                "EmptyStatement" => {}
+                "ExpressionStatement"
+                | "ReturnStatement"
+                | "IfStatement"
+                | "BlockStatement"
+                | "WhileStatement"
+                | "DoWhileStatement"
+                | "ForStatement"
+                | "ForOfStatement"
+                | "LabeledStatement"
+                | "BreakStatement"
+                | "ContinueStatement"
+                | "ThrowStatement"
+                | "TryStatement" => stmts.push(Expr::new(stmt)?),
+                _ => return Err(stmt.error(ParseErrorKind::UnsupportedNode)),
+            }
+        }
+
+        Ok(match stmts.len() {
+            0 => None,
+            1 => stmts.pop(),
+            _ => {
+                let span = match (stmts[0].span, stmts[stmts.len() - 1].span) {
+                    (Some(first), Some(last)) => Some(Span {
+                        start: first.start,
+                        end: last.end,
+                    }),
+                    _ => None,
+                };
+                Some(Expr::from_kind(ExprKind::Seq(stmts), span))
+            }
+        })
+    }
+
+    fn eval(&self, env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        grow_stack(|| self.eval_kind(env, cx)).map_err(|unwind| match unwind {
+            Unwind::Error(err) => Unwind::Error(err.or_at(self.span)),
+            unwind => unwind,
+        })
+    }
+
+    /// `eval` at the top level of a `Session`, leaving `env` with the
+    /// declarations that came into scope along the way.
+    fn eval_top(&self, env: &mut Environ, cx: &Interp) -> Result<Value, Unwind> {
+        let result = match &self.kind {
+            ExprKind::Seq(stmts) => stmts
+                .iter()
+                .try_fold(Value::Unit, |_, stmt| stmt.eval_top(env, cx)),
+            ExprKind::Bind(expr) => {
+                *env = cx.alloc_decls(env, &expr.decls);
+                cx.init_decls(env, &expr.decls)
+                    .and_then(|()| expr.body.eval_top(env, cx))
+            }
+            _ => return self.eval(env, cx),
+        };
+        result.map_err(|unwind| match unwind {
+            Unwind::Error(err) => Unwind::Error(err.or_at(self.span)),
+            unwind => unwind,
+        })
+    }
+
+    /// Arms that need locals of their own are kept in functions of their
+    /// own, so the frame of this one, on the stack for every level of
+    /// nesting, stays small.
+    fn eval_kind(&self, env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        use ExprKind::*;
+        use Value::*;
+        // I really hate this but don't really have time to find a better way
+        match &self.kind {
+            Binary(expr) => Expr::eval_binary(expr, env, cx),
+            Unary(expr) => Expr::eval_unary(expr, env, cx),
+            Conditional(expr) => Expr::eval_conditional(expr, env, cx),
+            ExprKind::Fn(expr) => Ok(Value::Fn(FnValue {
+                env: env.clone(),
+                func: Rc::clone(expr),
+                args: Rc::new([]),
+            })),
+            Bind(expr) => Expr::eval_bind(expr, env, cx),
+            Assign(expr) => Expr::eval_assign(expr, env, cx),
+            Update(expr) => Expr::eval_update(expr, env, cx),
+            Call(expr) => Expr::eval_call(expr, env, cx),
+            Member(expr) => Expr::eval_member(expr, env, cx),
+            ExprKind::Object(expr) => Expr::eval_object(expr, env, cx),
+            ExprKind::Array(expr) => Expr::eval_array(expr, env, cx),
+            Template(expr) => Expr::eval_template(expr, env, cx),
+            Seq(stmts) => Expr::eval_seq(stmts, env, cx),
+            Return(expr) => Err(Unwind::Return(expr.eval(env, cx)?)),
+            Loop(expr) => Expr::eval_loop(expr, env, cx),
+            ForOf(expr) => Expr::eval_for_of(expr, env, cx),
+            Labeled(label, body) => match body.eval(env, cx) {
+                Err(Unwind::Break(Some(target), _)) if target == *label => Ok(Unit),
+                result => result,
+            },
+            Break(label) => Err(Unwind::Break(label.clone(), self.span)),
+            Continue(label) => Err(Unwind::Continue(label.clone(), self.span)),
+            Throw(expr) => Err(Unwind::Throw(expr.eval(env, cx)?, self.span)),
+            Try(expr) => Expr::eval_try(expr, env, cx),
+            // a generator's body is run by `resume`, which only leaves the
+            // `yield`s it can't suspend from to here
+            Yield(_) => Err(EvalErrorKind::MisplacedYield.into()),
+            Ref(ident) => Ok(cx.load(&cx.lookup(env, ident)?, ident)?),
+            Literal(val) => Ok(val.clone()),
+        }
+    }
+
+    fn eval_binary(expr: &BinaryExpr, env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        use BinOp::*;
+        match expr.op {
+            // `&&` stops at a falsy lhs and `||` at a truthy one,
+            // otherwise both give their rhs
+            And | Or => {
+                let lhs = expr.lhs.eval(env, cx)?;
+                if cx.truthy(&lhs, &expr.op)? == matches!(expr.op, Or) {
+                    return Ok(lhs);
+                }
+                let rhs = expr.rhs.eval(env, cx)?;
+                if cx.opts.semantics == Semantics::Strict {
+                    cx.truthy(&rhs, &expr.op)?;
+                }
+                Ok(rhs)
+            }
+            ref op => {
+                let lhs = expr.lhs.eval(env, cx)?;
+                let rhs = expr.rhs.eval(env, cx)?;
+                Ok(cx.binary(op, lhs, rhs)?)
+            }
+        }
+    }
+
+    fn eval_unary(expr: &UnaryExpr, env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        let arg = expr.expr.eval(env, cx)?;
+        Ok(cx.unary(&expr.op, arg)?)
+    }
+
+    fn eval_conditional(expr: &CondExpr, env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        let test = expr.test.eval(env, cx)?;
+        if cx.truthy(&test, &"?:")? {
+            expr.cons.eval(env, cx)
+        } else {
+            expr.altr.eval(env, cx)
+        }
+    }
+
+    fn eval_bind(expr: &BindExpr, env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        let new_env = cx.declare(env, &expr.decls)?;
+        let result = expr.body.eval(&new_env, cx);
+        cx.release(new_env, env);
+        result
+    }
+
+    fn eval_assign(expr: &AssignExpr, env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        let place = cx.place(&expr.target, env)?;
+        // a compound assignment reads the old value first
+        let val = match &expr.op {
+            Some(op) => {
+                let old = cx.read(&place)?;
+                let rhs = expr.value.eval(env, cx)?;
+                cx.binary(op, old, rhs)?
+            }
+            None => expr.value.eval(env, cx)?,
+        };
+        cx.write(place, val.clone())?;
+        Ok(val)
+    }
+
+    fn eval_update(expr: &UpdateExpr, env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        let place = cx.place(&expr.target, env)?;
+        let old = cx.read(&place)?;
+        let old = match cx.opts.semantics {
+            Semantics::Js => js::to_number(&old, cx.opts.numbers),
+            Semantics::Strict => old,
+        };
+        let new = cx.binary(&expr.op, old.clone(), Value::Int(1))?;
+        cx.write(place, new.clone())?;
+        Ok(if expr.prefix { new } else { old })
+    }
+
+    fn eval_call(expr: &CallExpr, env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        let callee = match &expr.callee.kind {
+            ExprKind::Member(member) => {
+                let object = member.object.eval(env, cx)?;
+                let key = cx.key(&member.property, env)?;
+                cx.method(object, key)?
+            }
+            _ => Callee::Fn(expr.callee.eval(env, cx)?),
+        };
+        let args = expr
+            .args
+            .iter()
+            .map(|arg| arg.eval(env, cx))
+            .collect::<Result<_, _>>()?;
+        cx.call(callee, args)
+    }
+
+    fn eval_member(expr: &MemberExpr, env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        let object = expr.object.eval(env, cx)?;
+        let key = cx.key(&expr.property, env)?;
+        Ok(cx.get_property(object, key)?)
+    }
+
+    fn eval_object(expr: &ObjectExpr, env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        let mut obj = ObjectValue::default();
+        for (key, val) in &expr.props {
+            let key = cx.key(key, env)?;
+            obj.set(key, val.eval(env, cx)?);
+        }
+        Ok(Value::Object(Rc::new(RefCell::new(obj))))
+    }
+
+    fn eval_array(expr: &ArrayExpr, env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        let elems = expr
+            .elems
+            .iter()
+            .map(|elem| elem.eval(env, cx))
+            .collect::<Result<_, _>>()?;
+        Ok(Value::Array(Rc::new(RefCell::new(elems))))
+    }
+
+    fn eval_template(expr: &TemplateExpr, env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        let vals = expr
+            .exprs
+            .iter()
+            .map(|sub| sub.eval(env, cx))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Value::String(expr.fill(&vals)))
+    }
+
+    fn eval_seq(stmts: &[Expr], env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        let mut result = Value::Unit;
+        for stmt in stmts {
+            result = stmt.eval(env, cx)?;
+        }
+        Ok(result)
+    }
+
+    fn eval_loop(expr: &LoopExpr, outer: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        let base = cx.declare(outer, &expr.decls)?;
+        if let Some(init) = &expr.init {
+            init.eval(&base, cx)?;
+        }
+
+        let idents = expr.fresh_idents();
+        let mut env = base.clone();
+        let mut result = Value::Unit;
+        let mut first = true;
+        loop {
+            let next = cx.rebind(&base, &env, &idents);
+            cx.release(std::mem::replace(&mut env, next), &base);
+            if !first {
+                if let Some(update) = &expr.update {
+                    update.eval(&env, cx)?;
+                }
+            }
+            let skip_test = first && expr.kind == LoopKind::DoWhile;
+            first = false;
+            if let (Some(test), false) = (&expr.test, skip_test) {
+                let test = test.eval(&env, cx)?;
+                if !cx.truthy(&test, &expr.kind)? {
+                    break;
+                }
+            }
+
+            match expr.body.eval(&env, cx) {
+                Ok(val) => result = val,
+                Err(Unwind::Break(label, _)) if targets(&expr.labels, &label) => break,
+                Err(Unwind::Continue(label, _)) if targets(&expr.labels, &label) => {}
+                Err(unwind) => return Err(unwind),
+            }
+        }
+        cx.release(env, &base);
+        cx.release(base, outer);
+        Ok(result)
+    }
+
+    fn eval_for_of(expr: &ForOfExpr, env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        let mut iter = cx.iter(expr.right.eval(env, cx)?)?;
+        let env = cx.scope_each(&expr.left, env);
+        let mut result = Value::Unit;
+        loop {
+            let (val, done) = iter.next(Value::Unit, cx)?;
+            if done {
+                break;
+            }
+            // leaving early closes a generator, running its `finally`s
+            let body_env = match cx.bind_each(&expr.left, &env, val) {
+                Ok(body_env) => body_env,
+                Err(unwind) => return iter.leave(cx, Err(unwind)),
+            };
+            let body_result = expr.body.eval(&body_env, cx);
+            cx.release(body_env, &env);
+            match body_result {
+                Ok(val) => result = val,
+                Err(Unwind::Break(label, _)) if targets(&expr.labels, &label) => {
+                    return iter.leave(cx, Ok(result))
+                }
+                Err(Unwind::Continue(label, _)) if targets(&expr.labels, &label) => {}
+                Err(unwind) => return iter.leave(cx, Err(unwind)),
+            }
+        }
+        Ok(result)
+    }
+
+    fn eval_try(expr: &TryExpr, env: &Environ, cx: &Interp) -> Result<Value, Unwind> {
+        let mut result = expr.block.eval(env, cx);
+        let caught = result.as_ref().err().and_then(|unwind| cx.caught(unwind));
+        if let (Some(val), Some((param, handler))) = (caught, &expr.handler) {
+            result = match param {
+                Some(param) => cx.bind(env, param, val).and_then(|catch_env| {
+                    let result = handler.eval(&catch_env, cx);
+                    cx.release(catch_env, env);
+                    result
+                }),
+                None => handler.eval(env, cx),
+            };
+        }
+        // the `finally` block runs however the rest finished, and
+        // only changes the outcome by finishing early itself
+        match &expr.finalizer {
+            Some(finalizer) => finalizer.eval(env, cx).and(result),
+            None => result,
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        grow_stack(|| self.fmt_kind(f))
+    }
+}
+
+impl Expr {
+    fn fmt_kind(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use ExprKind::*;
+        match &self.kind {
+            Binary(expr) => {
+                use BinOp::*;
+                let op_type = match expr.op {
+                    Add | Sub | Mul | Div | Rem | BitOr | BitAnd | BitXor | Shl | Shr | UShr => {
+                        "arithmetic"
+                    }
+                    Or | And => "logical",
+                    Eq | Ne | StrictEq | StrictNe | Lt | Le | Gt | Ge => "relational",
+                };
+                write!(f, "({} {} {} {})", op_type, expr.op, expr.lhs, expr.rhs)
+            }
+            Unary(expr) => {
+                write!(f, "(unary {} {})", expr.op, expr.expr)
+            }
+            Conditional(expr) => {
+                write!(f, "(conditional {} {} {})", expr.test, expr.cons, expr.altr)
+            }
+            Fn(expr) => {
+                let star = if expr.generator { "*" } else { "" };
+                write!(f, "(fn{} (", star)?;
+                for (i, param) in expr.params.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    match &param.default {
+                        Some(default) => write!(f, "(default {} {})", param.pattern, default)?,
+                        None => write!(f, "{}", param.pattern)?,
+                    }
+                }
+                if let Some(rest) = &expr.rest {
+                    if !expr.params.is_empty() {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "(rest {})", rest)?;
+                }
+                write!(f, ") {})", expr.body)
+            }
+            Literal(val) => {
+                write!(f, "{}", val)
+            }
+            Ref(ident) => {
+                write!(f, "{}", ident)
+            }
+            Member(expr) => {
+                write!(f, "(member {} {})", expr.object, expr.property)
+            }
+            Object(expr) => {
+                write!(f, "(object")?;
+                for (key, val) in &expr.props {
+                    write!(f, " [{} {}]", key, val)?;
+                }
+                write!(f, ")")
+            }
+            Template(expr) => {
+                write!(f, "(template {}", quote(&expr.quasis[0]))?;
+                for (sub, quasi) in expr.exprs.iter().zip(&expr.quasis[1..]) {
+                    write!(f, " {} {}", sub, quote(quasi))?;
+                }
+                write!(f, ")")
+            }
+            Seq(stmts) => {
+                write!(f, "(seq")?;
+                for stmt in stmts {
+                    write!(f, " {}", stmt)?;
+                }
+                write!(f, ")")
+            }
+            Array(expr) => {
+                write!(f, "(array")?;
+                for elem in &expr.elems {
+                    write!(f, " {}", elem)?;
+                }
+                write!(f, ")")
+            }
+            Return(expr) => write!(f, "(return {})", expr),
+            Loop(expr) => {
+                write!(f, "(")?;
+                for label in &expr.labels {
+                    write!(f, "{}: ", label)?;
+                }
+                write!(f, "{}", expr.kind)?;
+                if expr.kind == LoopKind::For {
+                    write!(f, " (")?;
+                    for (i, decl) in expr.decls.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, " ")?;
+                        }
+                        write!(f, "{} = {}", decl.pattern, decl.init)?;
+                    }
+                    if let Some(init) = &expr.init {
+                        write!(f, "{}", init)?;
+                    }
+                    write!(f, ")")?;
+                }
+                for part in [&expr.test, &expr.update] {
+                    match part {
+                        Some(part) => write!(f, " {}", part)?,
+                        None if expr.kind == LoopKind::For => write!(f, " ()")?,
+                        None => {}
+                    }
+                }
+                write!(f, " {})", expr.body)
+            }
+            Labeled(label, body) => write!(f, "({}: {})", label, body),
+            Break(Some(label)) => write!(f, "(break {})", label),
+            Break(None) => write!(f, "(break)"),
+            Continue(Some(label)) => write!(f, "(continue {})", label),
+            Continue(None) => write!(f, "(continue)"),
+            Throw(expr) => write!(f, "(throw {})", expr),
+            Yield(expr) => {
+                let star = if expr.delegate { "*" } else { "" };
+                match &expr.arg {
+                    Some(arg) => write!(f, "(yield{} {})", star, arg),
+                    None => write!(f, "(yield)"),
+                }
+            }
+            ForOf(expr) => {
+                write!(f, "(")?;
+                for label in &expr.labels {
+                    write!(f, "{}: ", label)?;
+                }
+                write!(f, "for-of {} {} {})", expr.left, expr.right, expr.body)
+            }
+            Try(expr) => {
+                write!(f, "(try {}", expr.block)?;
+                match &expr.handler {
+                    Some((Some(param), handler)) => write!(f, " (catch {} {})", param, handler)?,
+                    Some((None, handler)) => write!(f, " (catch {})", handler)?,
+                    None => {}
+                }
+                if let Some(finalizer) = &expr.finalizer {
+                    write!(f, " (finally {})", finalizer)?;
+                }
+                write!(f, ")")
+            }
+            Call(expr) => {
+                write!(f, "(call {}", expr.callee)?;
+                for arg in &expr.args {
+                    write!(f, " {}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Bind(expr) => {
+                write!(f, "(let ")?;
+                for decl in &expr.decls {
+                    write!(f, "{} = {}, ", decl.pattern, decl.init)?;
+                }
+                write!(f, "{})", expr.body)
+            }
+            Assign(expr) => match &expr.op {
+                Some(op) => write!(f, "(assign {}= {} {})", op, expr.target, expr.value),
+                None => write!(f, "(assign {} {})", expr.target, expr.value),
+            },
+            Update(expr) => {
+                let op = match expr.op {
+                    BinOp::Add => "++",
+                    _ => "--",
+                };
+                let fix = if expr.prefix { "prefix" } else { "postfix" };
+                write!(f, "({} {} {})", fix, op, expr.target)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Program {
+    statement: Expr,
+}
+
+impl Program {
+    /// Build a program out of an ESTree `Program` node, as produced by
+    /// `acorn --ecma2024`.
+    pub fn from_estree(json: &serde_json::Value) -> Result<Self, ParseError> {
+        let root = Node::root(json, "");
+        let statement = match Expr::from_body(&root.array("body")?)? {
+            Some(statement) => statement,
+            None => Expr::unit(root.span()),
+        };
+
+        Ok(Program { statement })
+    }
+
+    /// Evaluate the program in the global environment, with the default
+    /// `Options`.
+    pub fn eval(&self) -> Result<Value, EvalError> {
+        self.eval_with(Options::default())
+    }
+
+    /// Evaluate the program in the global environment.
+    pub fn eval_with(&self, opts: Options) -> Result<Value, EvalError> {
+        let cx = Interp::new(opts, Store::default());
+        finish(self.statement.eval(&globals(&cx), &cx))
+    }
+}
+
+/// Programs evaluated one after another, each in the scope of what the ones
+/// before it declared, as the lines of a repl are.
+pub struct Session {
+    opts: Options,
+    env: Environ,
+    /// The values of the bindings in `env`, and of those closures made.
+    store: Store,
+}
+
+impl Session {
+    /// A session starting from the global environment.
+    pub fn new(opts: Options) -> Self {
+        let cx = Interp::new(opts, Store::default());
+        Session {
+            opts,
+            env: globals(&cx),
+            store: cx.store.into_inner(),
+        }
+    }
+
+    /// Evaluate `program`, keeping its top level declarations for the
+    /// programs after it. A program that fails keeps the ones it got to,
+    /// even those left uninitialized.
+    pub fn eval(&mut self, program: &Program) -> Result<Value, EvalError> {
+        let cx = Interp::new(self.opts, std::mem::take(&mut self.store));
+        let result = finish(program.statement.eval_top(&mut self.env, &cx));
+        self.store = cx.store.into_inner();
+        result
+    }
+}
+
+/// What a program that evaluated to `result` gives.
+fn finish(result: Result<Value, Unwind>) -> Result<Value, EvalError> {
+    match result {
+        // a `return` outside any function ends the program
+        Ok(val) | Err(Unwind::Return(val)) => Ok(val),
+        Err(Unwind::Throw(value, span)) => Err(EvalError {
+            kind: EvalErrorKind::Uncaught { value },
+            span,
+        }),
+        Err(unwind) => match unwind.stray() {
+            Unwind::Error(err) => Err(err),
+            _ => unreachable!("stray only leaves errors"),
+        },
+    }
+}
+
+/// The environment programs start in, binding the values javascript has
+/// names for rather than literals.
+fn globals(cx: &Interp) -> Environ {
+    [
+        ("undefined", Value::Unit),
+        ("NaN", Value::Float(f64::NAN)),
+        ("Infinity", Value::Float(f64::INFINITY)),
+    ]
+    .into_iter()
+    .fold(Environ::empty(), |env, (ident, val)| {
+        let binding = Binding {
+            loc: cx.store.borrow_mut().alloc(Some(val)),
+            constant: true,
+        };
+        env.extend(ident.to_owned(), binding)
+    })
+}
+
+/// How deep calls can nest before evaluation gives up with
+/// `EvalErrorKind::StackOverflow`.
+const MAX_CALL_DEPTH: usize = 10_000;
+
+/// How close to the end of the stack evaluation can get before going on
+/// in a new segment of it.
+const RED_ZONE: usize = 256 << 10;
+
+/// Size of each new segment of stack, enough for a good many calls of a
+/// debug build.
+const STACK_SEGMENT: usize = 8 << 20;
+
+/// Run `f`, on a new segment of stack if this one is close to running out,
+/// so how deep evaluation can go is only limited by `MAX_CALL_DEPTH`.
+pub(crate) fn grow_stack<T>(f: impl FnOnce() -> T) -> T {
+    stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, f)
+}
+
+/// Why evaluation of an expression stopped short of giving its value.
+#[derive(Debug)]
+enum Unwind {
+    Error(EvalError),
+    /// A `return` is leaving the function it is in with this value.
+    Return(Value),
+    /// A `break`, at this span, is leaving the loop or statement with this
+    /// label, or the innermost loop.
+    Break(Option<String>, Option<Span>),
+    /// A `continue`, at this span, is going on to the next iteration of the
+    /// loop with this label, or the innermost loop.
+    Continue(Option<String>, Option<Span>),
+    /// A value was thrown, by the `throw` at this span, and is looking for a
+    /// `catch`.
+    Throw(Value, Option<Span>),
+    /// A generator body is suspending at a `yield` of this value.
+    Yield(Value),
+}
+
+impl Unwind {
+    /// `self` leaving a function body or the program, which turns a `break`
+    /// or `continue` that never found its target into an error.
+    fn stray(self) -> Self {
+        let (jump, span) = match self {
+            Unwind::Break(Some(label), span) => (format!("break {}", label), span),
+            Unwind::Break(None, span) => ("break".to_owned(), span),
+            Unwind::Continue(Some(label), span) => (format!("continue {}", label), span),
+            Unwind::Continue(None, span) => ("continue".to_owned(), span),
+            unwind => return unwind,
+        };
+        Unwind::Error(EvalError::from(EvalErrorKind::NoJumpTarget { jump }).or_at(span))
+    }
+}
+
+impl From<EvalError> for Unwind {
+    fn from(err: EvalError) -> Self {
+        Unwind::Error(err)
+    }
+}
+
+impl From<EvalErrorKind> for Unwind {
+    fn from(kind: EvalErrorKind) -> Self {
+        Unwind::Error(kind.into())
+    }
+}
+
+/// What a call calls: a function value, or a built-in method, which isn't a
+/// value and only exists to be called.
+enum Callee {
+    Fn(Value),
+    Array(ArrayMethod, Rc<RefCell<Vec<Value>>>),
+    /// A generator's `next`.
+    Next(Rc<RefCell<Generator>>),
+}
+
+/// State shared by the whole of one evaluation.
+struct Interp {
+    opts: Options,
+    /// How many calls deep evaluation is.
+    depth: Cell<usize>,
+    store: RefCell<Store>,
+}
+
+impl Interp {
+    fn new(opts: Options, store: Store) -> Self {
+        Interp {
+            opts,
+            depth: Cell::new(0),
+            store: RefCell::new(store),
+        }
+    }
+
+    /// `f()`, evaluated a call deeper, or an error once that's deeper than
+    /// `MAX_CALL_DEPTH`.
+    fn nested<T>(&self, f: impl FnOnce() -> Result<T, Unwind>) -> Result<T, Unwind> {
+        let depth = self.depth.get();
+        if depth >= MAX_CALL_DEPTH {
+            return Err(EvalErrorKind::StackOverflow { depth }.into());
+        }
+        self.depth.set(depth + 1);
+        let result = f();
+        self.depth.set(depth);
+        result
+    }
+
+    /// The value a `catch` gets for `unwind`, if it catches it: what was
+    /// thrown, or under javascript semantics, an error as the `name` and
+    /// `message` of the javascript error it would be.
+    fn caught(&self, unwind: &Unwind) -> Option<Value> {
+        match unwind {
+            Unwind::Throw(val, _) => Some(val.clone()),
+            Unwind::Error(err) if self.opts.semantics == Semantics::Js => {
+                let mut obj = ObjectValue::default();
+                obj.set(
+                    "name".to_owned(),
+                    Value::String(err.kind.js_name()?.to_owned()),
+                );
+                obj.set("message".to_owned(), Value::String(err.to_string()));
+                Some(Value::Object(Rc::new(RefCell::new(obj))))
+            }
+            _ => None,
+        }
+    }
+
+    /// `env` with the names in `pattern` bound to new, assignable
+    /// locations holding the parts of `val` they match.
+    fn bind(&self, env: &Environ, pattern: &Pattern, val: Value) -> Result<Environ, Unwind> {
+        let new_env = self.alloc(env, pattern, false);
+        self.destructure(pattern, val, &new_env)?;
+        Ok(new_env)
+    }
+
+    /// `env` with the names in `decls` bound. Every name is in scope of
+    /// every initializer, which lets functions refer to themselves and each
+    /// other, but reading one before its initializer has run is an error.
+    fn declare(&self, env: &Environ, decls: &[Decl]) -> Result<Environ, Unwind> {
+        let new_env = self.alloc_decls(env, decls);
+        self.init_decls(&new_env, decls)?;
+        Ok(new_env)
+    }
+
+    /// Run the initializers of `decls`, already bound in `env`.
+    fn init_decls(&self, env: &Environ, decls: &[Decl]) -> Result<(), Unwind> {
+        for decl in decls {
+            let bound_val = decl.init.eval(env, self)?;
+            self.destructure(&decl.pattern, bound_val, env)?;
+        }
+        Ok(())
+    }
+
+    /// `env` with the names in `decls` bound to new locations without a
+    /// value yet.
+    fn alloc_decls(&self, env: &Environ, decls: &[Decl]) -> Environ {
+        decls.iter().fold(env.clone(), |new_env, decl| {
+            self.alloc(&new_env, &decl.pattern, decl.constant)
+        })
+    }
+
+    /// `env` with the names in `pattern` bound to new locations without a
+    /// value yet.
+    fn alloc(&self, env: &Environ, pattern: &Pattern, constant: bool) -> Environ {
+        pattern
+            .idents()
+            .into_iter()
+            .fold(env.clone(), |new_env, ident| {
+                let binding = Binding {
+                    loc: self.store.borrow_mut().alloc(None),
+                    constant,
+                };
+                new_env.extend(ident.to_owned(), binding)
+            })
+    }
+
+    /// Give the names in `pattern`, already bound in `env`, the parts of
+    /// `val` they match.
+    ///
+    /// A missing property or element is undefined when there's a default
+    /// for it, or under javascript semantics, and an error otherwise.
+    fn destructure(&self, pattern: &Pattern, val: Value, env: &Environ) -> Result<(), Unwind> {
+        let missing_ok = |pattern: &Pattern| {
+            matches!(pattern, Pattern::Default(..)) || self.opts.semantics == Semantics::Js
+        };
+
+        match pattern {
+            Pattern::Ident(ident) => {
+                let binding = env.lookup(ident).expect("pattern names are bound first");
+                self.store.borrow_mut().set(binding.loc, val);
+            }
+            Pattern::Default(pattern, default) => {
+                let val = match val {
+                    Value::Unit => default.eval(env, self)?,
+                    val => val,
+                };
+                self.destructure(pattern, val, env)?;
+            }
+            Pattern::Object { props, rest } => {
+                let obj = match (&val, self.opts.semantics) {
+                    (Value::Object(obj), _) => Some(obj),
+                    // anything but null and undefined has properties to
+                    // javascript
+                    (Value::Null | Value::Unit, _) | (_, Semantics::Strict) => {
+                        return Err(EvalErrorKind::Destructure {
+                            expected: "object",
+                            found: val,
+                        }
+                        .into())
+                    }
+                    _ => None,
+                };
+
+                let mut taken = Vec::new();
+                for (key, pattern) in props {
+                    let key = self.key(key, env)?;
+                    let prop = match obj {
+                        Some(obj) => obj.borrow().get(&key).cloned(),
+                        None => Some(self.get_property(val.clone(), key.clone())?),
+                    };
+                    let prop = match prop {
+                        Some(prop) => prop,
+                        None if missing_ok(pattern) => Value::Unit,
+                        None => {
+                            return Err(EvalErrorKind::NoProperty {
+                                object: val,
+                                property: key,
+                            }
+                            .into())
+                        }
+                    };
+                    self.destructure(pattern, prop, env)?;
+                    taken.push(key);
+                }
+
+                if let Some(rest) = rest {
+                    let mut rest_obj = ObjectValue::default();
+                    if let Some(obj) = obj {
+                        for (key, prop) in obj.borrow().iter() {
+                            if !taken.iter().any(|taken| taken == key) {
+                                rest_obj.set(key.to_owned(), prop.clone());
+                            }
+                        }
+                    }
+                    let rest_val = Value::Object(Rc::new(RefCell::new(rest_obj)));
+                    self.destructure(&Pattern::Ident(rest.clone()), rest_val, env)?;
+                }
+            }
+            Pattern::Array { elems, rest } => {
+                let vals = match &val {
+                    Value::Array(vals) => vals.borrow().clone(),
+                    _ => {
+                        return Err(EvalErrorKind::Destructure {
+                            expected: "array",
+                            found: val,
+                        }
+                        .into())
+                    }
+                };
+
+                for (i, pattern) in elems.iter().enumerate() {
+                    let Some(pattern) = pattern else {
+                        continue;
+                    };
+                    let elem = match vals.get(i) {
+                        Some(elem) => elem.clone(),
+                        None if missing_ok(pattern) => Value::Unit,
+                        None => {
+                            return Err(EvalErrorKind::OutOfBounds {
+                                index: i as i64,
+                                len: vals.len(),
+                            }
+                            .into())
+                        }
+                    };
+                    self.destructure(pattern, elem, env)?;
+                }
+
+                if let Some(rest) = rest {
+                    let rest_vals = vals.get(elems.len()..).unwrap_or_default().to_vec();
+                    let rest_val = Value::Array(Rc::new(RefCell::new(rest_vals)));
+                    self.destructure(rest, rest_val, env)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `base` with each of `idents` bound again, to a new location holding
+    /// the value it has in `env`, an environment built on `base`.
+    ///
+    /// Rebinding on `base` rather than `env` keeps a loop's environment from
+    /// growing with every iteration.
+    fn rebind(&self, base: &Environ, env: &Environ, idents: &[&str]) -> Environ {
+        let mut new_env = base.clone();
+        for ident in idents {
+            let Some(binding) = env.lookup(ident) else {
+                continue;
+            };
+            let val = self.store.borrow().get(binding.loc).cloned();
+            let binding = Binding {
+                loc: self.store.borrow_mut().alloc(val),
+                ..binding
+            };
+            new_env = new_env.extend(ident.to_string(), binding);
+        }
+        new_env
+    }
+
+    /// Done with `env`, built on `outer`, free the locations it binds in
+    /// front of `outer` if nothing can see them any more.
+    ///
+    /// Nothing can if nothing holds on to their frames but `env` and
+    /// closures stored in those very locations, as a function bound to a
+    /// name it calls itself by is.
+    fn release(&self, env: Environ, outer: &Environ) {
+        let locs = env.locs_before(outer);
+        let unseen = {
+            let store = self.store.borrow();
+            let held: Vec<&Environ> = locs
+                .iter()
+                .filter_map(|loc| match store.get(*loc) {
+                    Some(Value::Fn(func)) => Some(&func.env),
+                    _ => None,
+                })
+                .collect();
+            env.held_only_by(outer, &held)
+        };
+        if unseen {
+            // dropped once the store is free to be used again
+            let _vals: Vec<_> = locs
+                .into_iter()
+                .map(|loc| self.store.borrow_mut().free(loc))
+                .collect();
+        }
+    }
+
+    fn lookup(&self, env: &Environ, ident: &str) -> Result<Binding, EvalError> {
+        env.lookup(ident).ok_or_else(|| {
+            EvalErrorKind::Unbound {
+                ident: ident.to_owned(),
+                suggestion: env.closest(ident).map(str::to_owned),
+            }
+            .into()
+        })
+    }
+
+    /// The value of `binding`, the binding of `ident`.
+    fn load(&self, binding: &Binding, ident: &str) -> Result<Value, EvalError> {
+        match self.store.borrow().get(binding.loc) {
+            Some(val) => Ok(val.clone()),
+            None => Err(EvalErrorKind::Uninitialized {
+                ident: ident.to_owned(),
+            }
+            .into()),
+        }
+    }
+
+    /// Give `binding`, the binding of `ident`, the value `val`.
+    fn assign(&self, binding: Binding, ident: &str, val: Value) -> Result<(), EvalError> {
+        if binding.constant {
+            return Err(EvalErrorKind::AssignToConst {
+                ident: ident.to_owned(),
+            }
+            .into());
+        }
+        self.load(&binding, ident)?;
+        self.store.borrow_mut().set(binding.loc, val);
+        Ok(())
+    }
+
+    /// Evaluate the object and key of `target`, to read or write it.
+    fn place(&self, target: &Target, env: &Environ) -> Result<Place, Unwind> {
+        match target {
+            Target::Ident(ident) => Ok(Place::Binding(self.lookup(env, ident)?, ident.clone())),
+            Target::Member(expr) => {
+                let object = expr.object.eval(env, self)?;
+                Ok(Place::Property(object, self.key(&expr.property, env)?))
+            }
+        }
+    }
+
+    fn read(&self, place: &Place) -> Result<Value, EvalError> {
+        match place {
+            Place::Binding(binding, ident) => self.load(binding, ident),
+            Place::Property(object, key) => self.get_property(object.clone(), key.clone()),
+        }
+    }
+
+    fn write(&self, place: Place, val: Value) -> Result<(), EvalError> {
+        match place {
+            Place::Binding(binding, ident) => self.assign(binding, &ident, val),
+            Place::Property(Value::Object(obj), key) => {
+                obj.borrow_mut().set(key, val);
+                Ok(())
+            }
+            // one past the end appends, and under javascript semantics, any
+            // further fills the gap with undefined
+            Place::Property(Value::Array(elems), key) if index(&key).is_some() => {
+                let mut elems = elems.borrow_mut();
+                let len = elems.len();
+                match index(&key).expect("checked above") {
+                    i if (0..len as i64).contains(&i) => elems[i as usize] = val,
+                    i if i == len as i64 => elems.push(val),
+                    i if i > len as i64
+                        && self.opts.semantics == Semantics::Js
+                        && elems.try_reserve(i as usize + 1 - len).is_ok() =>
+                    {
+                        elems.resize(i as usize, Value::Unit);
+                        elems.push(val);
+                    }
+                    i => {
+                        return Err(EvalErrorKind::OutOfBounds {
+                            index: i,
+                            len: elems.len(),
+                        }
+                        .into())
+                    }
+                }
+                Ok(())
+            }
+            Place::Property(object, property) => {
+                Err(EvalErrorKind::SetProperty { object, property }.into())
+            }
+        }
+    }
+
+    /// The property name `key` stands for.
+    fn key(&self, key: &Key, env: &Environ) -> Result<String, Unwind> {
+        match key {
+            Key::Named(name) => Ok(name.clone()),
+            Key::Computed(expr) => Ok(self.property_name(expr.eval(env, self)?)?),
+        }
+    }
+
+    /// The property name a computed key with the value `val` stands for.
+    /// Strict semantics only take strings and numbers.
+    fn property_name(&self, val: Value) -> Result<String, EvalError> {
+        match (self.opts.semantics, val) {
+            (_, Value::String(name)) => Ok(name),
+            (Semantics::Strict, val @ (Value::Int(_) | Value::Float(_))) | (Semantics::Js, val) => {
+                Ok(js::to_string(&val))
+            }
+            (Semantics::Strict, val) => Err(EvalErrorKind::TypeMismatch {
+                op: "[]".to_owned(),
+                operands: vec![val],
+            }
+            .into()),
+        }
+    }
+
+    /// The property `key` of `object`. Javascript semantics give undefined
+    /// for a missing property of anything but undefined itself.
+    fn get_property(&self, object: Value, key: String) -> Result<Value, EvalError> {
+        let found = match (&object, key.as_str()) {
+            // javascript counts utf-16 code units
+            (Value::String(v), "length") => Some(num::from_int(
+                v.encode_utf16().count() as f64,
+                self.opts.numbers,
+            )),
+            (Value::Object(obj), _) => obj.borrow().get(&key).cloned(),
+            (Value::Array(elems), "length") => Some(num::from_int(
+                elems.borrow().len() as f64,
+                self.opts.numbers,
+            )),
+            (Value::Array(elems), _) => match index(&key) {
+                Some(i) => {
+                    let elems = elems.borrow();
+                    match usize::try_from(i).ok().and_then(|i| elems.get(i)) {
+                        Some(elem) => Some(elem.clone()),
+                        // undefined, under javascript semantics
+                        None if self.opts.semantics == Semantics::Js => None,
+                        None => {
+                            return Err(EvalErrorKind::OutOfBounds {
+                                index: i,
+                                len: elems.len(),
+                            }
+                            .into())
+                        }
+                    }
+                }
+                None => None,
+            },
+            _ => None,
+        };
+        match (found, self.opts.semantics, &object) {
+            (Some(val), _, _) => Ok(val),
+            (None, Semantics::Js, object) if !matches!(object, Value::Null | Value::Unit) => {
+                Ok(Value::Unit)
+            }
+            _ => Err(EvalErrorKind::NoProperty {
+                object,
+                property: key,
+            }
+            .into()),
+        }
+    }
+
+    /// What a call of the property `key` of `object` calls.
+    fn method(&self, object: Value, key: String) -> Result<Callee, EvalError> {
+        Ok(match (object, ArrayMethod::from_name(&key)) {
+            (Value::Array(elems), Some(method)) => Callee::Array(method, elems),
+            (Value::Generator(gen), _) if key == "next" => Callee::Next(gen),
+            (object, _) => Callee::Fn(self.get_property(object, key)?),
+        })
+    }
+
+    fn call(&self, callee: Callee, args: Vec<Value>) -> Result<Value, Unwind> {
+        match callee {
+            Callee::Fn(Value::Fn(callee)) => callee.call(args, self),
+            Callee::Fn(callee) => Err(EvalErrorKind::NotCallable { callee }.into()),
+            Callee::Array(method, elems) => self.array_method(method, &elems, args),
+            Callee::Next(gen) => self.next(&gen, args),
+        }
+    }
+
+    /// Call the built-in `method` of the array `elems` with `args`.
+    ///
+    /// The callback sees each element as it is when its turn comes, up to
+    /// the length the array had to begin with.
+    fn array_method(
+        &self,
+        method: ArrayMethod,
+        elems: &Rc<RefCell<Vec<Value>>>,
+        args: Vec<Value>,
+    ) -> Result<Value, Unwind> {
+        let max = match method {
+            ArrayMethod::Reduce => 2,
+            _ => 1,
+        };
+        if args.is_empty() || args.len() > max {
+            return Err(EvalErrorKind::Arity {
+                min: 1,
+                max: Some(max),
+                found: args.len(),
+            }
+            .into());
+        }
+        let mut args = args.into_iter();
+        let callback = match args.next() {
+            Some(Value::Fn(callback)) => callback,
+            Some(callee) => return Err(EvalErrorKind::NotCallable { callee }.into()),
+            None => unreachable!("arity is checked above"),
+        };
+
+        let array = Value::Array(Rc::clone(elems));
+        let len = elems.borrow().len();
+        let mut acc = args.next();
+        let mut start = 0;
+        if let (ArrayMethod::Reduce, None) = (method, &acc) {
+            acc = Some(
+                elems
+                    .borrow()
+                    .first()
+                    .cloned()
+                    .ok_or(EvalErrorKind::EmptyReduce)?,
+            );
+            start = 1;
+        }
+
+        let mut result = Vec::new();
+        for i in start..len {
+            let elem = match elems.borrow().get(i) {
+                Some(elem) => elem.clone(),
+                None => break,
+            };
+            let i = num::from_int(i as f64, self.opts.numbers);
+            match method {
+                ArrayMethod::Map => {
+                    result.push(callback.call_back(vec![elem, i, array.clone()], self)?);
+                }
+                ArrayMethod::Filter => {
+                    let keep = callback.call_back(vec![elem.clone(), i, array.clone()], self)?;
+                    if self.truthy(&keep, &method)? {
+                        result.push(elem);
+                    }
+                }
+                ArrayMethod::Reduce => {
+                    let prev = acc.take().expect("reduce has an accumulator");
+                    acc = Some(callback.call_back(vec![prev, elem, i, array.clone()], self)?);
+                }
+                ArrayMethod::ForEach => {
+                    callback.call_back(vec![elem, i, array.clone()], self)?;
+                }
+            }
+        }
+
+        Ok(match method {
+            ArrayMethod::Map | ArrayMethod::Filter => Value::Array(Rc::new(RefCell::new(result))),
+            ArrayMethod::Reduce => acc.expect("reduce has an accumulator"),
+            ArrayMethod::ForEach => Value::Unit,
+        })
+    }
+
+    /// `env` for a `for...of` loop assigning to `left`, where a `var` is
+    /// bound once for the whole loop.
+    fn scope_each(&self, left: &ForOfLeft, env: &Environ) -> Environ {
+        match left {
+            ForOfLeft::Decl {
+                pattern,
+                constant,
+                per_iteration: false,
+            } => self.alloc(env, pattern, *constant),
+            _ => env.clone(),
+        }
+    }
+
+    /// Give `left` the value `val` for one iteration of a `for...of` loop,
+    /// `env` being from `scope_each`, and give the environment of the body.
+    fn bind_each(&self, left: &ForOfLeft, env: &Environ, val: Value) -> Result<Environ, Unwind> {
+        match left {
+            ForOfLeft::Decl {
+                pattern,
+                constant,
+                per_iteration: true,
+            } => {
+                let new_env = self.alloc(env, pattern, *constant);
+                self.destructure(pattern, val, &new_env)?;
+                Ok(new_env)
+            }
+            ForOfLeft::Decl { pattern, .. } => {
+                self.destructure(pattern, val, env)?;
+                Ok(env.clone())
+            }
+            ForOfLeft::Target(target) => {
+                let place = self.place(target, env)?;
+                self.write(place, val)?;
+                Ok(env.clone())
+            }
+        }
+    }
+
+    fn unary(&self, op: &UnaryOp, arg: Value) -> Result<Value, EvalError> {
+        use UnaryOp::*;
+        use Value::*;
+        let arg = match (self.opts.semantics, op) {
+            (Semantics::Js, Plus | Minus | BitNot) => js::to_number(&arg, self.opts.numbers),
+            _ => arg,
+        };
+        match op {
+            Plus => match arg {
+                Int(_) | Float(_) => Ok(arg),
+                arg => type_mismatch(op, vec![arg]),
+            },
+            Minus => match num::negate(&arg, self.opts.numbers) {
+                Some(result) => result,
+                None => type_mismatch(op, vec![arg]),
+            },
+            Not => Ok(Bool(!self.truthy(&arg, op)?)),
+            BitNot => match num::bit_not(&arg, self.opts.numbers) {
+                Some(result) => Ok(result),
+                None => type_mismatch(op, vec![arg]),
+            },
+        }
+    }
+
+    /// Any binary operator but `&&` and `||`, which need their rhs unevaluated.
+    fn binary(&self, op: &BinOp, lhs: Value, rhs: Value) -> Result<Value, EvalError> {
+        if self.opts.semantics == Semantics::Js {
+            return js::binary(op, &lhs, &rhs, self.opts.numbers);
+        }
+
+        use BinOp::*;
+        use Value::*;
+        let result = match (op, &lhs, &rhs) {
+            // string -> string
+            (Add, String(l), String(r)) => Some(Ok(String(l.clone() + r))),
+            // string -> boolean
+            (Lt | Le | Gt | Ge | Eq | Ne | StrictEq | StrictNe, String(l), String(r)) => {
+                js::compare_strings(op, l, r).map(|result| Ok(Bool(result)))
+            }
+            // number -> number
+            (Add | Sub | Mul | Div | Rem, _, _) => num::arith(op, &lhs, &rhs, self.opts.numbers),
+            // number -> boolean
+            (Lt | Le | Gt | Ge | Eq | Ne | StrictEq | StrictNe, _, _) => {
+                num::compare(op, &lhs, &rhs).map(|result| Ok(Bool(result)))
+            }
+            // int32 -> int32
+            (BitXor | BitOr | BitAnd | Shl | Shr | UShr, _, _) => {
+                num::bitwise(op, &lhs, &rhs, self.opts.numbers)
+            }
+            (And | Or, _, _) => None,
+        };
+        result.unwrap_or_else(|| type_mismatch(op, vec![lhs, rhs]))
+    }
+
+    /// Whether `val` counts as true, for `op`. Strict semantics only take
+    /// booleans.
+    fn truthy(&self, val: &Value, op: &impl Display) -> Result<bool, EvalError> {
+        match (self.opts.semantics, val) {
+            (Semantics::Js, val) => Ok(js::to_boolean(val)),
+            (Semantics::Strict, Value::Bool(v)) => Ok(*v),
+            (Semantics::Strict, val) => Err(EvalErrorKind::TypeMismatch {
+                op: op.to_string(),
+                operands: vec![val.clone()],
+            }
+            .into()),
+        }
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.statement)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::{globals, quote, Interp, Program, Session, Value};
+    use crate::{
+        env::Binding,
+        options::{NumberMode, Options, Semantics},
+        parser::parse,
+        span::Span,
+        store::Store,
+    };
+
+    /// The printed value of the javascript `src`, or its error, for the
+    /// tests of this module and those inside it.
+    pub(super) fn eval(src: &str) -> String {
+        eval_with(src, Options::default())
+    }
+
+    pub(super) fn eval_with(src: &str, opts: Options) -> String {
+        let json = parse(src).expect("test source parses");
+        let program = Program::from_estree(&json).expect("test source is supported");
+        match program.eval_with(opts) {
+            Ok(val) => val.to_string(),
+            Err(err) => format!("error: {}", err),
+        }
+    }
+
+    #[test]
+    fn undefined_argument_takes_default() {
+        assert_eq!(
+            eval("const f = (a = 1, b) => [a, b]; f(undefined, 2)"),
+            eval("[1, 2]")
+        );
+    }
+
+    #[test]
+    fn null_argument_keeps_null() {
+        assert_eq!(
+            eval("const f = (a = 1, b) => [a, b]; f(null, 2)"),
+            eval("[null, 2]")
+        );
+    }
+
+    #[test]
+    fn undefined_in_pattern_takes_default() {
+        assert_eq!(
+            eval(
+                "const { a = 1, b: [c = 2, d = 3] } = { a: undefined, b: [undefined, null] };
+                 [a, c, d]"
+            ),
+            eval("[1, 2, null]")
+        );
+    }
+
+    #[test]
+    fn undefined_in_parameter_pattern_takes_default() {
+        assert_eq!(
+            eval("const f = ({ x = 'dflt' }, [y = 0] = []) => [x, y]; f({ x: undefined })"),
+            eval("['dflt', 0]")
+        );
+    }
+
+    #[test]
+    fn session_keeps_bindings_and_their_values() {
+        let mut session = Session::new(Options::default());
+        let mut eval = |src: &str| {
+            let program = Program::from_estree(&parse(src).expect("test source parses"))
+                .expect("test source is supported");
+            match session.eval(&program) {
+                Ok(val) => val.to_string(),
+                Err(err) => format!("error: {}", err),
+            }
+        };
+        eval("let x = 1; const arr = [1, 2];");
+        eval("x = 5");
+        eval("arr[0] = 9");
+        assert_eq!(eval("[x, arr]"), "(value (array (value (number 5)) (value (array (value (number 9)) (value (number 2))))))");
+        eval("let y = z; const z = 1;");
+        assert_eq!(
+            eval("y"),
+            "error: `y` read before its declaration was evaluated"
+        );
+    }
+
+    #[test]
+    fn javascript_catches_errors_as_error_objects() {
+        let js = Options {
+            semantics: Semantics::Js,
+            ..Options::default()
+        };
+        assert_eq!(
+            eval_with("try { null.x } catch (e) { [e.name, e.message] }", js),
+            eval("['TypeError', 'no property `x` on (value null)']")
+        );
+        assert_eq!(
+            eval_with("try { nope } catch ({ name }) { name }", js),
+            eval("'ReferenceError'")
+        );
+        assert_eq!(
+            eval("try { null.x } catch (e) { 'caught' }"),
+            "error: no property `x` on (value null)"
+        );
+    }
+
+    #[test]
+    fn reading_past_the_end_of_an_array() {
+        let js = Options {
+            semantics: Semantics::Js,
+            ..Options::default()
+        };
+        assert_eq!(eval_with("[1, 2][5]", js), eval("undefined"));
+        assert_eq!(eval_with("[1, 2][-1]", js), eval("undefined"));
+        assert_eq!(
+            eval("[1, 2][5]"),
+            "error: index 5 out of bounds for array of length 2"
+        );
+    }
+
+    #[test]
+    fn writing_past_the_end_of_an_array() {
+        let js = Options {
+            semantics: Semantics::Js,
+            ..Options::default()
+        };
+        assert_eq!(
+            eval_with("const a = []; a[1] = 1; a", js),
+            eval("[undefined, 1]")
+        );
+        assert_eq!(
+            eval("const a = []; a[1] = 1; a"),
+            "error: index 1 out of bounds for array of length 0"
+        );
+        // one past the end appends either way
+        assert_eq!(eval("const a = [0]; a[1] = 1; a"), eval("[0, 1]"));
+        assert_eq!(
+            eval_with("const a = [0]; a[-1] = 1; a", js),
+            "error: index -1 out of bounds for array of length 1"
+        );
+    }
+
+    #[test]
+    fn stray_jump_is_an_error_at_the_jump() {
+        let src = "let x = 1;\nbreak;";
+        let program = Program::from_estree(&parse(src).expect("test source parses"))
+            .expect("test source is supported");
+        let err = program.eval().expect_err("a stray break fails");
+        assert_eq!(err.to_string(), "`break` has no loop or label to go to");
+        assert_eq!(err.span, Some(Span { start: 11, end: 17 }));
+
+        assert_eq!(
+            eval("const f = () => { continue outer }; f()"),
+            "error: `continue outer` has no loop or label to go to"
+        );
+    }
+
+    #[test]
+    fn json_keeps_key_order_and_integral_floats() {
+        let json = parse("({ z: 1.5 * 2, a: [0.5, -0], m: {} })").expect("test source parses");
+        let program = Program::from_estree(&json).expect("test source is supported");
+        let val = program.eval().expect("test source evaluates");
+        assert_eq!(val.to_json().to_string(), r#"{"z":3,"a":[0.5,0],"m":{}}"#);
+    }
+
+    #[test]
+    fn quote_escapes() {
+        assert_eq!(
+            quote("a\"b\\c\n\t\r\u{1}é"),
+            r#""a\"b\\c\n\t\u000D\u0001é""#
+        );
+    }
+
+    /// How many references to the array `probe` is bound to are left
+    /// after evaluating `src`, besides the one in the store.
+    fn probe_refs_after(src: &str) -> usize {
+        let probe = Rc::new(RefCell::new(Vec::new()));
+        let cx = Interp::new(Options::default(), Store::default());
+        let binding = Binding {
+            loc: cx
+                .store
+                .borrow_mut()
+                .alloc(Some(Value::Array(probe.clone()))),
+            constant: true,
+        };
+        let env = globals(&cx).extend("probe".to_owned(), binding);
+        let json = parse(src).expect("test source parses");
+        let program = Program::from_estree(&json).expect("test source is supported");
+        program
+            .statement
+            .eval(&env, &cx)
+            .expect("test source evaluates");
+        Rc::strong_count(&probe) - 2
+    }
+
+    #[test]
+    fn leaving_scope_frees_closures_that_see_themselves() {
+        let src = "for (let i = 0; i < 10; i++) {
+            const a = probe;
+            const f = () => [a, f, g], g = () => f;
+            f().length;
+        }";
+        assert_eq!(probe_refs_after(src), 0);
+    }
+
+    #[test]
+    fn leaving_scope_keeps_closures_that_escape() {
+        let src = "let keep = [];
+        for (let i = 0; i < 10; i++) {
+            const a = probe;
+            const f = () => [a, f];
+            keep[i] = f;
+        }";
+        assert_eq!(probe_refs_after(src), 10);
+    }
+
+    #[test]
+    fn calls_check_callee_and_arity() {
+        assert_eq!(eval("const add = (a, b) => a + b; add(1, 2)"), eval("3"));
+        assert_eq!(
+            eval("const x = 3; x()"),
+            "error: call of non function (value (number 3))"
+        );
+        assert_eq!(
+            eval("const add = (a, b) => a + b; add(1)"),
+            "error: wrong number of arguments in call: expected 2, found 1"
+        );
+        assert_eq!(
+            eval("const id = (a) => a; id(1, 2)"),
+            "error: wrong number of arguments in call: expected 1, found 2"
+        );
+    }
+
+    #[test]
+    fn closures_outlive_the_call_that_made_them() {
+        assert_eq!(
+            eval("const adder = (n) => (m) => n + m; const add2 = adder(2); adder(5); add2(1)"),
+            eval("3")
+        );
+        // each call makes its own counter
+        assert_eq!(
+            eval(
+                "const counter = () => { let c = 0; return () => { c = c + 1; return c; }; };
+                 const a = counter(), b = counter();
+                 a(); a(); b();
+                 [a(), b()]"
+            ),
+            eval("[3, 2]")
+        );
+    }
+
+    #[test]
+    fn unsupported_estree_is_an_error_at_its_path() {
+        let error = |expression: serde_json::Value| {
+            let json = serde_json::json!({
+                "type": "Program",
+                "body": [{ "type": "ExpressionStatement", "expression": expression }],
+            });
+            Program::from_estree(&json)
+                .expect_err("test input is unsupported")
+                .to_string()
+        };
+        assert_eq!(
+            error(serde_json::json!({ "type": "ClassExpression" })),
+            "unsupported node in ClassExpression at body[0].expression"
+        );
+        assert_eq!(
+            error(serde_json::json!({ "type": "Literal", "value": {} })),
+            "unsupported literal in Literal at body[0].expression"
+        );
+        assert_eq!(
+            error(serde_json::json!({
+                "type": "UnaryExpression",
+                "operator": "delete",
+                "prefix": true,
+                "argument": { "type": "Identifier", "name": "x" },
+            })),
+            "unsupported operator `delete` in UnaryExpression at body[0].expression"
+        );
+        assert_eq!(
+            error(serde_json::json!({ "type": "UnaryExpression", "operator": "-" })),
+            "missing field `argument` in UnaryExpression at body[0].expression"
+        );
+    }
+
+    #[test]
+    fn program_prints_and_evaluates_to_a_value() {
+        let json = parse("const f = (x) => x + 1; f(2)").expect("test source parses");
+        let program = Program::from_estree(&json).expect("test source is supported");
+        assert_eq!(
+            program.to_string(),
+            "(let f = (fn (x) (arithmetic + x (value (number 1)))), (call f (value (number 2))))"
+        );
+        assert!(matches!(program.eval(), Ok(Value::Int(3))));
+        let double = Options {
+            numbers: NumberMode::Double,
+            ..Options::default()
+        };
+        assert!(matches!(program.eval_with(double), Ok(Value::Float(n)) if n == 3.));
+    }
+
+    #[test]
+    fn arrow_bodies() {
+        let printed = |src: &str| {
+            Program::from_estree(&parse(src).expect("test source parses"))
+                .expect("test source is supported")
+                .to_string()
+        };
+        assert_eq!(
+            printed("(x) => x * 2"),
+            "(fn (x) (arithmetic * x (value (number 2))))"
+        );
+        assert_eq!(
+            printed("(x) => { return x * 2; }"),
+            "(fn (x) (return (arithmetic * x (value (number 2)))))"
+        );
+        assert_eq!(eval("((x) => ({ a: x }))(3).a"), eval("3"));
+    }
+
+    #[test]
+    fn declarations_see_each_other() {
+        assert_eq!(
+            eval(
+                "const even = (n) => n === 0 ? true : odd(n - 1),
+                       odd = (n) => n === 0 ? false : even(n - 1);
+                 [even(10), odd(7)]"
+            ),
+            eval("[true, true]")
+        );
+        assert_eq!(
+            eval("const fact = (n) => n <= 1 ? 1 : n * fact(n - 1); fact(5)"),
+            eval("120")
+        );
+    }
+
+    #[test]
+    fn reading_a_declaration_before_it_runs() {
+        assert_eq!(
+            eval("const a = b, b = 1; a"),
+            "error: `b` read before its declaration was evaluated"
+        );
+        // a closure may refer ahead, as long as it isn't called too soon
+        assert_eq!(
+            eval("const f = () => y, z = f(), y = 2; z"),
+            "error: `y` read before its declaration was evaluated"
+        );
+        assert_eq!(eval("const f = () => y, y = 2; f()"), eval("2"));
+    }
+
+    #[test]
+    fn assigning_to_bindings() {
+        assert_eq!(
+            eval("let x = 1; x += 2; x++; [x, x++, ++x, x -= 1]"),
+            eval("[4, 4, 6, 5]")
+        );
+        for src in [
+            "const x = 1; x = 2",
+            "const x = 1; x *= 2",
+            "const x = 1; x--",
+        ] {
+            assert_eq!(eval(src), "error: assignment to constant `x`", "{}", src);
+        }
+        // a closure assigns to the binding it sees, not a copy of it
+        assert_eq!(
+            eval("let n = 0; const bump = () => { n += 1; }; bump(); bump(); n"),
+            eval("2")
+        );
+    }
+
+    #[test]
+    fn statements_run_in_order_and_blocks_scope() {
+        assert_eq!(
+            eval("let log = []; log[0] = 1; log[1] = 2; log[2] = 3; log"),
+            eval("[1, 2, 3]")
+        );
+        assert_eq!(eval("const x = 1; { const x = 2; } x"), eval("1"));
+        assert_eq!(eval("{ const y = 2; } y"), "error: unbound identifier `y`");
+        assert_eq!(
+            eval(
+                "const sign = (n) => { if (n > 0) { return 'pos'; } return 'neg'; };
+                 [sign(1), sign(-1)]"
+            ),
+            eval("['pos', 'neg']")
+        );
+    }
+
+    #[test]
+    fn loop_closures_see_their_own_iteration() {
+        assert_eq!(
+            eval(
+                "const fs = [];
+                 for (let i = 0; i < 3; i++) { fs[i] = () => i; }
+                 [fs[0](), fs[1](), fs[2]()]"
+            ),
+            eval("[0, 1, 2]")
+        );
+        assert_eq!(
+            eval(
+                "const fs = [];
+                 for (let x of ['a', 'b']) { fs[fs.length] = () => x; }
+                 [fs[0](), fs[1]()]"
+            ),
+            eval("['a', 'b']")
+        );
+    }
+
+    #[test]
+    fn loops_and_labelled_jumps() {
+        assert_eq!(
+            eval("let i = 0, n = 0; do { n += i; i++; } while (i < 4); n"),
+            eval("6")
+        );
+        assert_eq!(
+            eval("let i = 0; while (true) { if (i === 5) break; i++; } i"),
+            eval("5")
+        );
+        assert_eq!(
+            eval(
+                "let out = [];
+                 outer: for (let i = 0; i < 3; i++) {
+                     for (let j = 0; j < 3; j++) {
+                         if (j === 1) continue outer;
+                         if (i === 2) break outer;
+                         out[out.length] = [i, j];
+                     }
+                 }
+                 out"
+            ),
+            eval("[[0, 0], [1, 0]]")
+        );
+    }
+
+    #[test]
+    fn higher_order_array_methods() {
+        assert_eq!(eval("[1, 2, 3].map((x) => x * 2)"), eval("[2, 4, 6]"));
+        assert_eq!(eval("[1, 2].map((x, i) => i)"), eval("[0, 1]"));
+        assert_eq!(
+            eval("[1, 2, 3, 4].filter((x) => x % 2 === 0)"),
+            eval("[2, 4]")
+        );
+        assert_eq!(eval("[1, 2, 3].reduce((a, b) => a + b)"), eval("6"));
+        assert_eq!(eval("[1, 2, 3].reduce((a, b) => a + b, 10)"), eval("16"));
+        assert_eq!(
+            eval("[].reduce((a, b) => a + b)"),
+            "error: reduce of empty array with no initial value"
+        );
+        assert_eq!(
+            eval("let s = 0; [1, 2].forEach((x, i) => { s += x * 10 + i; }); s"),
+            eval("31")
+        );
+    }
+
+    #[test]
+    fn destructuring_takes_elements_and_rests() {
+        assert_eq!(
+            eval("const [a, ...rest] = [1, 2, 3]; [a, rest]"),
+            eval("[1, [2, 3]]")
+        );
+        assert_eq!(
+            eval("const { a, b: { c }, ...o } = { a: 1, b: { c: 2 }, d: 3 }; [a, c, o]"),
+            eval("[1, 2, { d: 3 }]")
+        );
+    }
+
+    #[test]
+    fn destructuring_the_wrong_shape() {
+        assert_eq!(
+            eval("const [a, b] = 5"),
+            "error: cannot destructure (value (number 5)) with an array pattern"
+        );
+        assert_eq!(
+            eval("const { a } = null"),
+            "error: cannot destructure (value null) with an object pattern"
+        );
+        assert_eq!(
+            eval("const [a] = {}"),
+            "error: cannot destructure (value (object)) with an array pattern"
+        );
+        assert_eq!(
+            eval("const [a, [b]] = [1, 2]"),
+            "error: cannot destructure (value (number 2)) with an array pattern"
+        );
+        assert_eq!(
+            eval("const f = ([x]) => x; f(1)"),
+            "error: cannot destructure (value (number 1)) with an array pattern"
+        );
     }
 }
@@ -0,0 +1,896 @@
+// Generators, and the second way of evaluating expressions they need.
+//
+// `Expr::eval` keeps its place on the rust stack, which a `yield` can't leave
+// and come back to. `Expr::resume` evaluates a generator body the same way,
+// except that when a `yield` suspends it, each expression it passes on the
+// way out saves where it was in a `Frame`. The next `next` walks back down
+// those frames to the `yield` and carries on from there. Anything without a
+// `yield` inside is left to `eval`.
+
+use std::{cell::RefCell, rc::Rc};
+
+use super::{
+    grow_stack, targets, BinOp, Callee, Decl, Environ, Expr, ExprKind, FnExpr, Interp, Key,
+    LoopKind, MemberExpr, ObjectValue, Place, TryExpr, Unwind, Value,
+};
+use crate::{
+    error::{EvalError, EvalErrorKind},
+    options::Semantics,
+};
+
+/// A call of a `function*`, suspended between `next`s.
+#[derive(Debug)]
+pub struct Generator {
+    func: Rc<FnExpr>,
+    /// The environment of the call, with the arguments bound.
+    env: Environ,
+    state: GenState,
+}
+
+#[derive(Debug)]
+enum GenState {
+    /// Waiting for a `next`, at the `yield` these frames lead to, or at the
+    /// start of the body when there are none.
+    Suspended(Vec<Frame>),
+    /// In a `next`.
+    Running,
+    Done,
+}
+
+impl Generator {
+    pub(super) fn new(func: Rc<FnExpr>, env: Environ) -> Self {
+        Generator {
+            func,
+            env,
+            state: GenState::Suspended(Vec::new()),
+        }
+    }
+}
+
+/// Where a suspended generator body carries on from.
+struct Resume {
+    /// A frame for each expression between the body and the `yield`,
+    /// innermost first, so the next to resume is last.
+    frames: Vec<Frame>,
+    /// What the `yield` does on resuming.
+    sent: Sent,
+}
+
+/// How a suspended generator is resumed.
+#[derive(Debug)]
+enum Sent {
+    /// By a `next`, its `yield` giving this value.
+    Value(Value),
+    /// By closing it, its `yield` returning, through any `finally` around
+    /// it.
+    Return,
+}
+
+impl Resume {
+    /// The frame of the expression being resumed, or `None` when it's being
+    /// evaluated afresh.
+    fn pop(&mut self) -> Option<Frame> {
+        self.frames.pop()
+    }
+
+    /// What the `yield` being resumed gives, or how it returns.
+    fn take_sent(&mut self) -> Result<Value, Unwind> {
+        match std::mem::replace(&mut self.sent, Sent::Value(Value::Unit)) {
+            Sent::Value(val) => Ok(val),
+            Sent::Return => Err(Unwind::Return(Value::Unit)),
+        }
+    }
+
+    /// Which part the expression being resumed was in, `0` when it's being
+    /// evaluated afresh.
+    fn part(&mut self) -> usize {
+        match self.frames.pop() {
+            None => 0,
+            Some(Frame::Part(part)) => part,
+            Some(frame) => mismatch(frame),
+        }
+    }
+}
+
+/// `result`, with `frame()` saved on the way out should it be a `yield`
+/// suspending.
+fn suspend<T>(
+    result: Result<T, Unwind>,
+    r: &mut Resume,
+    frame: impl FnOnce() -> Frame,
+) -> Result<T, Unwind> {
+    if let Err(Unwind::Yield(_)) = result {
+        r.frames.push(frame());
+    }
+    result
+}
+
+fn mismatch(frame: Frame) -> ! {
+    unreachable!("resumed with the frame of another expression: {:?}", frame)
+}
+
+/// Where evaluation of one expression was when a `yield` inside it suspended.
+#[derive(Debug)]
+enum Frame {
+    /// At the `yield` itself.
+    Yield,
+    /// In a `yield*`, handing on the values of `iter`.
+    Delegate(Iter),
+    /// In one part of an expression made of parts evaluated one after
+    /// another, like the statements of a sequence or the test and branches
+    /// of a conditional.
+    Part(usize),
+    /// In one of the operands of an expression, with the values of the ones
+    /// before it.
+    Operands(Vec<Value>),
+    /// In the initializer of one of a run of declarations, all of them bound
+    /// in the environment.
+    Decl(Environ, usize),
+    /// In the body of the scope of some declarations.
+    Scope(Environ),
+    /// In the value of an assignment to `place`, with the value it had
+    /// before when the assignment is compound.
+    Assign(Place, Option<Value>),
+    /// In a stage of a loop, with the value of the body so far.
+    Loop {
+        /// The environment of the whole loop, with its declarations bound.
+        base: Environ,
+        /// The environment of this iteration.
+        env: Environ,
+        stage: Stage,
+        result: Value,
+    },
+    /// In the body of a `for...of` loop.
+    ForOf {
+        iter: Iter,
+        /// The environment of the whole loop.
+        env: Environ,
+        /// The environment of this iteration.
+        body_env: Environ,
+        result: Value,
+    },
+    /// In the `catch` clause of a `try`, in its environment.
+    Catch(Environ),
+    /// In the `finally` clause of a `try`, which finishes as `result` unless
+    /// it finishes early itself.
+    Finally(Result<Value, Unwind>),
+}
+
+/// The parts of a loop, in the order they run.
+#[derive(Debug, Clone, Copy)]
+enum Stage {
+    Decls,
+    Init,
+    Update,
+    Test,
+    Body,
+}
+
+/// Something `for...of` and `yield*` take values from one at a time.
+#[derive(Debug)]
+pub(super) enum Iter {
+    /// An array, and the index of its next element.
+    Array(Rc<RefCell<Vec<Value>>>, usize),
+    Generator(Rc<RefCell<Generator>>),
+}
+
+impl Iter {
+    /// The next value, and whether there are no more, the value then being
+    /// what a generator returned. `sent` is what a generator's `yield`
+    /// gives.
+    pub(super) fn next(&mut self, sent: Value, cx: &Interp) -> Result<(Value, bool), Unwind> {
+        match self {
+            // the array is read as it is now, so it can grow along the way
+            Iter::Array(elems, i) => match elems.borrow().get(*i) {
+                Some(elem) => {
+                    *i += 1;
+                    Ok((elem.clone(), false))
+                }
+                None => Ok((Value::Unit, true)),
+            },
+            Iter::Generator(gen) => cx.resume(gen, Sent::Value(sent)),
+        }
+    }
+
+    /// Close `self`, left before it ran out, so a generator runs its
+    /// `finally`s and is done.
+    fn close(&mut self, cx: &Interp) -> Result<(), Unwind> {
+        match self {
+            Iter::Array(..) => Ok(()),
+            Iter::Generator(gen) => cx.resume(gen, Sent::Return).map(|_| ()),
+        }
+    }
+
+    /// `result`, the way a loop over `self` was left early, once `self` is
+    /// closed. Failing to close replaces anything but a throw or an error.
+    pub(super) fn leave(
+        &mut self,
+        cx: &Interp,
+        result: Result<Value, Unwind>,
+    ) -> Result<Value, Unwind> {
+        let closed = self.close(cx);
+        match result {
+            Err(Unwind::Throw(..) | Unwind::Error(_)) => result,
+            result => closed.and(result),
+        }
+    }
+}
+
+impl Interp {
+    /// The values of `val`, for `for...of` or `yield*`.
+    pub(super) fn iter(&self, val: Value) -> Result<Iter, EvalError> {
+        match val {
+            Value::Array(elems) => Ok(Iter::Array(elems, 0)),
+            Value::Generator(gen) => Ok(Iter::Generator(gen)),
+            value => Err(EvalErrorKind::NotIterable { value }.into()),
+        }
+    }
+
+    /// The `next` of `gen`, giving an object with the `value` it yielded or
+    /// returned and whether it's `done`.
+    pub(super) fn next(
+        &self,
+        gen: &Rc<RefCell<Generator>>,
+        args: Vec<Value>,
+    ) -> Result<Value, Unwind> {
+        if self.opts.semantics == Semantics::Strict && args.len() > 1 {
+            return Err(EvalErrorKind::Arity {
+                min: 0,
+                max: Some(1),
+                found: args.len(),
+            }
+            .into());
+        }
+        let sent = args.into_iter().next().unwrap_or(Value::Unit);
+
+        let (value, done) = self.resume(gen, Sent::Value(sent))?;
+        let mut result = ObjectValue::default();
+        result.set("value".to_owned(), value);
+        result.set("done".to_owned(), Value::Bool(done));
+        Ok(Value::Object(Rc::new(RefCell::new(result))))
+    }
+
+    /// Run `gen` until its next `yield` or the end of its body, giving the
+    /// value yielded or returned and whether it's done. A generator that
+    /// threw is done too.
+    fn resume(&self, gen: &Rc<RefCell<Generator>>, sent: Sent) -> Result<(Value, bool), Unwind> {
+        // not borrowed while the body runs, which may get at `gen` again
+        let (func, env, frames) = {
+            let mut gen = gen.borrow_mut();
+            match std::mem::replace(&mut gen.state, GenState::Running) {
+                // closed before it started, there's nothing to run
+                GenState::Suspended(frames)
+                    if frames.is_empty() && matches!(sent, Sent::Return) =>
+                {
+                    gen.state = GenState::Done;
+                    return Ok((Value::Unit, true));
+                }
+                GenState::Suspended(frames) => (Rc::clone(&gen.func), gen.env.clone(), frames),
+                GenState::Running => return Err(EvalErrorKind::GeneratorRunning.into()),
+                GenState::Done => {
+                    gen.state = GenState::Done;
+                    return Ok((Value::Unit, true));
+                }
+            }
+        };
+
+        let mut r = Resume { frames, sent };
+        let (state, result) = match self.nested(|| func.body.resume(&env, self, &mut r)) {
+            Err(Unwind::Yield(val)) => (GenState::Suspended(r.frames), Ok((val, false))),
+            Ok(val) | Err(Unwind::Return(val)) => (GenState::Done, Ok((val, true))),
+            Err(unwind) => (GenState::Done, Err(unwind.stray())),
+        };
+        gen.borrow_mut().state = state;
+        result
+    }
+
+    /// The values of `exprs`, evaluated one after another.
+    fn operands(
+        &self,
+        exprs: &[&Expr],
+        env: &Environ,
+        r: &mut Resume,
+    ) -> Result<Vec<Value>, Unwind> {
+        let mut vals = match r.pop() {
+            Some(Frame::Operands(vals)) => vals,
+            None => Vec::new(),
+            Some(frame) => mismatch(frame),
+        };
+        while let Some(expr) = exprs.get(vals.len()) {
+            match expr.resume(env, self, r) {
+                Ok(val) => vals.push(val),
+                Err(Unwind::Yield(val)) => {
+                    r.frames.push(Frame::Operands(vals));
+                    return Err(Unwind::Yield(val));
+                }
+                Err(unwind) => return Err(unwind),
+            }
+        }
+        Ok(vals)
+    }
+
+    /// The object and property name of `expr`, out of the values of its
+    /// `member_operands`.
+    fn member(
+        &self,
+        expr: &MemberExpr,
+        vals: &mut impl Iterator<Item = Value>,
+    ) -> Result<(Value, String), EvalError> {
+        let object = vals.next().expect("a value for each operand");
+        let key = match &expr.property {
+            Key::Named(name) => name.clone(),
+            Key::Computed(_) => {
+                self.property_name(vals.next().expect("a value for each operand"))?
+            }
+        };
+        Ok((object, key))
+    }
+
+    /// `declare`, for a generator body.
+    fn resume_declare(
+        &self,
+        env: &Environ,
+        decls: &[Decl],
+        r: &mut Resume,
+    ) -> Result<Environ, Unwind> {
+        let (new_env, start) = match r.pop() {
+            Some(Frame::Decl(new_env, i)) => (new_env, i),
+            None => (self.alloc_decls(env, decls), 0),
+            Some(frame) => mismatch(frame),
+        };
+        for (i, decl) in decls.iter().enumerate().skip(start) {
+            let val = suspend(decl.init.resume(&new_env, self, r), r, || {
+                Frame::Decl(new_env.clone(), i)
+            })?;
+            self.destructure(&decl.pattern, val, &new_env)?;
+        }
+        Ok(new_env)
+    }
+}
+
+/// The expressions a member expression evaluates, in order: its object and
+/// any computed key.
+fn member_operands(expr: &MemberExpr) -> Vec<&Expr> {
+    let mut operands = vec![&expr.object];
+    if let Key::Computed(key) = &expr.property {
+        operands.push(key);
+    }
+    operands
+}
+
+impl TryExpr {
+    fn resume_catch(&self, env: Environ, cx: &Interp, r: &mut Resume) -> Result<Value, Unwind> {
+        let (_, handler) = self.handler.as_ref().expect("only a `catch` catches");
+        match handler.resume(&env, cx, r) {
+            Err(Unwind::Yield(val)) => {
+                r.frames.push(Frame::Catch(env));
+                Err(Unwind::Yield(val))
+            }
+            result => result,
+        }
+    }
+
+    fn resume_finally(
+        &self,
+        env: &Environ,
+        cx: &Interp,
+        r: &mut Resume,
+        result: Result<Value, Unwind>,
+    ) -> Result<Value, Unwind> {
+        let Some(finalizer) = &self.finalizer else {
+            return result;
+        };
+        match finalizer.resume(env, cx, r) {
+            Err(Unwind::Yield(val)) => {
+                r.frames.push(Frame::Finally(result));
+                Err(Unwind::Yield(val))
+            }
+            finished => finished.and(result),
+        }
+    }
+}
+
+impl Expr {
+    /// Evaluate `self` as part of a generator body, carrying on from where
+    /// `r` says when resuming from a `yield`. A `yield` suspends evaluation
+    /// with `Unwind::Yield`, leaving `r` saying where.
+    fn resume(&self, env: &Environ, cx: &Interp, r: &mut Resume) -> Result<Value, Unwind> {
+        if !self.yields {
+            return self.eval(env, cx);
+        }
+        grow_stack(|| self.resume_kind(env, cx, r)).map_err(|unwind| match unwind {
+            Unwind::Error(err) => Unwind::Error(err.or_at(self.span)),
+            unwind => unwind,
+        })
+    }
+
+    fn resume_kind(&self, env: &Environ, cx: &Interp, r: &mut Resume) -> Result<Value, Unwind> {
+        use ExprKind::*;
+        match &self.kind {
+            Yield(expr) => {
+                let (mut iter, sent) = match r.pop() {
+                    Some(Frame::Yield) => return r.take_sent(),
+                    // closing hands on to the generator being delegated to
+                    Some(Frame::Delegate(mut iter)) => match r.take_sent() {
+                        Ok(sent) => (iter, sent),
+                        Err(unwind) => return iter.leave(cx, Err(unwind)),
+                    },
+                    None | Some(Frame::Part(0)) => {
+                        let val = match &expr.arg {
+                            Some(arg) => suspend(arg.resume(env, cx, r), r, || Frame::Part(0))?,
+                            None => Value::Unit,
+                        };
+                        if !expr.delegate {
+                            r.frames.push(Frame::Yield);
+                            return Err(Unwind::Yield(val));
+                        }
+                        (cx.iter(val)?, Value::Unit)
+                    }
+                    Some(frame) => mismatch(frame),
+                };
+                // `yield*` gives what the generator it hands on returns
+                let (val, done) = iter.next(sent, cx)?;
+                if done {
+                    return Ok(val);
+                }
+                r.frames.push(Frame::Delegate(iter));
+                Err(Unwind::Yield(val))
+            }
+            Seq(stmts) => {
+                let start = r.part();
+                let mut result = Value::Unit;
+                for (i, stmt) in stmts.iter().enumerate().skip(start) {
+                    result = suspend(stmt.resume(env, cx, r), r, || Frame::Part(i))?;
+                }
+                Ok(result)
+            }
+            Bind(expr) => {
+                let new_env = match r.pop() {
+                    Some(Frame::Scope(new_env)) => new_env,
+                    None | Some(Frame::Part(0)) => {
+                        suspend(cx.resume_declare(env, &expr.decls, r), r, || Frame::Part(0))?
+                    }
+                    Some(frame) => mismatch(frame),
+                };
+                suspend(expr.body.resume(&new_env, cx, r), r, || {
+                    Frame::Scope(new_env.clone())
+                })
+            }
+            Conditional(expr) => {
+                let part = match r.part() {
+                    0 => {
+                        let test = suspend(expr.test.resume(env, cx, r), r, || Frame::Part(0))?;
+                        if cx.truthy(&test, &"?:")? {
+                            1
+                        } else {
+                            2
+                        }
+                    }
+                    part => part,
+                };
+                let branch = if part == 1 { &expr.cons } else { &expr.altr };
+                suspend(branch.resume(env, cx, r), r, || Frame::Part(part))
+            }
+            Binary(expr) => match expr.op {
+                BinOp::And | BinOp::Or => {
+                    if r.part() == 0 {
+                        let lhs = suspend(expr.lhs.resume(env, cx, r), r, || Frame::Part(0))?;
+                        if cx.truthy(&lhs, &expr.op)? == matches!(expr.op, BinOp::Or) {
+                            return Ok(lhs);
+                        }
+                    }
+                    let rhs = suspend(expr.rhs.resume(env, cx, r), r, || Frame::Part(1))?;
+                    if cx.opts.semantics == Semantics::Strict {
+                        cx.truthy(&rhs, &expr.op)?;
+                    }
+                    Ok(rhs)
+                }
+                ref op => {
+                    let mut vals = cx.operands(&[&expr.lhs, &expr.rhs], env, r)?.into_iter();
+                    let (Some(lhs), Some(rhs)) = (vals.next(), vals.next()) else {
+                        unreachable!("a value for each operand");
+                    };
+                    Ok(cx.binary(op, lhs, rhs)?)
+                }
+            },
+            Unary(expr) => {
+                let arg = expr.expr.resume(env, cx, r)?;
+                Ok(cx.unary(&expr.op, arg)?)
+            }
+            Assign(expr) => {
+                let (place, old) = match r.pop() {
+                    Some(Frame::Assign(place, old)) => (place, old),
+                    None => {
+                        let place = cx.place(&expr.target, env)?;
+                        let old = match expr.op {
+                            Some(_) => Some(cx.read(&place)?),
+                            None => None,
+                        };
+                        (place, old)
+                    }
+                    Some(frame) => mismatch(frame),
+                };
+                let rhs = match expr.value.resume(env, cx, r) {
+                    Err(Unwind::Yield(val)) => {
+                        r.frames.push(Frame::Assign(place, old));
+                        return Err(Unwind::Yield(val));
+                    }
+                    result => result?,
+                };
+                let val = match (&expr.op, old) {
+                    (Some(op), Some(old)) => cx.binary(op, old, rhs)?,
+                    _ => rhs,
+                };
+                cx.write(place, val.clone())?;
+                Ok(val)
+            }
+            Call(expr) => {
+                let mut operands = match &expr.callee.kind {
+                    Member(member) => member_operands(member),
+                    _ => vec![&expr.callee],
+                };
+                operands.extend(&expr.args);
+                let mut vals = cx.operands(&operands, env, r)?.into_iter();
+                let callee = match &expr.callee.kind {
+                    Member(member) => {
+                        let (object, key) = cx.member(member, &mut vals)?;
+                        cx.method(object, key)?
+                    }
+                    _ => Callee::Fn(vals.next().expect("a value for each operand")),
+                };
+                cx.call(callee, vals.collect())
+            }
+            Member(expr) => {
+                let vals = cx.operands(&member_operands(expr), env, r)?;
+                let (object, key) = cx.member(expr, &mut vals.into_iter())?;
+                Ok(cx.get_property(object, key)?)
+            }
+            Object(expr) => {
+                let mut operands = Vec::new();
+                for (key, val) in &expr.props {
+                    if let Key::Computed(key) = key {
+                        operands.push(key);
+                    }
+                    operands.push(val);
+                }
+                let mut vals = cx.operands(&operands, env, r)?.into_iter();
+
+                let mut obj = ObjectValue::default();
+                for (key, _) in &expr.props {
+                    let key = match key {
+                        Key::Named(name) => name.clone(),
+                        Key::Computed(_) => {
+                            cx.property_name(vals.next().expect("a value for each operand"))?
+                        }
+                    };
+                    obj.set(key, vals.next().expect("a value for each operand"));
+                }
+                Ok(Value::Object(Rc::new(RefCell::new(obj))))
+            }
+            Array(expr) => {
+                let elems: Vec<_> = expr.elems.iter().collect();
+                let elems = cx.operands(&elems, env, r)?;
+                Ok(Value::Array(Rc::new(RefCell::new(elems))))
+            }
+            Template(expr) => {
+                let exprs: Vec<_> = expr.exprs.iter().collect();
+                let vals = cx.operands(&exprs, env, r)?;
+                Ok(Value::String(expr.fill(&vals)))
+            }
+            Return(expr) => Err(Unwind::Return(expr.resume(env, cx, r)?)),
+            Throw(expr) => Err(Unwind::Throw(expr.resume(env, cx, r)?, self.span)),
+            Labeled(label, body) => match body.resume(env, cx, r) {
+                Err(Unwind::Break(Some(target), _)) if target == *label => Ok(Value::Unit),
+                result => result,
+            },
+            Loop(expr) => {
+                let idents = expr.fresh_idents();
+                let (mut base, mut env, mut stage, mut result) = match r.pop() {
+                    Some(Frame::Loop {
+                        base,
+                        env,
+                        stage,
+                        result,
+                    }) => (base, env, stage, result),
+                    None => (env.clone(), env.clone(), Stage::Decls, Value::Unit),
+                    Some(frame) => mismatch(frame),
+                };
+                let frame = |base: &Environ, env: &Environ, stage, result: &Value| Frame::Loop {
+                    base: base.clone(),
+                    env: env.clone(),
+                    stage,
+                    result: result.clone(),
+                };
+                // the stages run as they do in `eval`
+                loop {
+                    stage = match stage {
+                        Stage::Decls => {
+                            base = suspend(cx.resume_declare(&env, &expr.decls, r), r, || {
+                                frame(&base, &env, Stage::Decls, &Value::Unit)
+                            })?;
+                            env = base.clone();
+                            Stage::Init
+                        }
+                        Stage::Init => {
+                            if let Some(init) = &expr.init {
+                                suspend(init.resume(&env, cx, r), r, || {
+                                    frame(&base, &env, Stage::Init, &Value::Unit)
+                                })?;
+                            }
+                            env = cx.rebind(&base, &env, &idents);
+                            match expr.kind {
+                                LoopKind::DoWhile => Stage::Body,
+                                _ => Stage::Test,
+                            }
+                        }
+                        Stage::Update => {
+                            if let Some(update) = &expr.update {
+                                suspend(update.resume(&env, cx, r), r, || {
+                                    frame(&base, &env, Stage::Update, &result)
+                                })?;
+                            }
+                            Stage::Test
+                        }
+                        Stage::Test => {
+                            if let Some(test) = &expr.test {
+                                let test = suspend(test.resume(&env, cx, r), r, || {
+                                    frame(&base, &env, Stage::Test, &result)
+                                })?;
+                                if !cx.truthy(&test, &expr.kind)? {
+                                    return Ok(result);
+                                }
+                            }
+                            Stage::Body
+                        }
+                        Stage::Body => {
+                            match expr.body.resume(&env, cx, r) {
+                                Ok(val) => result = val,
+                                Err(Unwind::Yield(val)) => {
+                                    r.frames.push(Frame::Loop {
+                                        base,
+                                        env,
+                                        stage: Stage::Body,
+                                        result,
+                                    });
+                                    return Err(Unwind::Yield(val));
+                                }
+                                Err(Unwind::Break(label, _)) if targets(&expr.labels, &label) => {
+                                    return Ok(result)
+                                }
+                                Err(Unwind::Continue(label, _))
+                                    if targets(&expr.labels, &label) => {}
+                                Err(unwind) => return Err(unwind),
+                            }
+                            env = cx.rebind(&base, &env, &idents);
+                            Stage::Update
+                        }
+                    };
+                }
+            }
+            ForOf(expr) => {
+                let (mut iter, env, mut resumed, mut result) = match r.pop() {
+                    Some(Frame::ForOf {
+                        iter,
+                        env,
+                        body_env,
+                        result,
+                    }) => (iter, env, Some(body_env), result),
+                    None | Some(Frame::Part(0)) => {
+                        let right = suspend(expr.right.resume(env, cx, r), r, || Frame::Part(0))?;
+                        let iter = cx.iter(right)?;
+                        (iter, cx.scope_each(&expr.left, env), None, Value::Unit)
+                    }
+                    Some(frame) => mismatch(frame),
+                };
+                loop {
+                    let body_env = match resumed.take() {
+                        Some(body_env) => body_env,
+                        None => {
+                            let (val, done) = iter.next(Value::Unit, cx)?;
+                            if done {
+                                return Ok(result);
+                            }
+                            match cx.bind_each(&expr.left, &env, val) {
+                                Ok(body_env) => body_env,
+                                Err(unwind) => return iter.leave(cx, Err(unwind)),
+                            }
+                        }
+                    };
+                    match expr.body.resume(&body_env, cx, r) {
+                        Ok(val) => result = val,
+                        Err(Unwind::Yield(val)) => {
+                            r.frames.push(Frame::ForOf {
+                                iter,
+                                env,
+                                body_env,
+                                result,
+                            });
+                            return Err(Unwind::Yield(val));
+                        }
+                        Err(Unwind::Break(label, _)) if targets(&expr.labels, &label) => {
+                            return iter.leave(cx, Ok(result))
+                        }
+                        Err(Unwind::Continue(label, _)) if targets(&expr.labels, &label) => {}
+                        Err(unwind) => return iter.leave(cx, Err(unwind)),
+                    }
+                }
+            }
+            Try(expr) => {
+                let result = match r.pop() {
+                    None | Some(Frame::Part(0)) => {
+                        let result = suspend(expr.block.resume(env, cx, r), r, || Frame::Part(0));
+                        let caught = result.as_ref().err().and_then(|unwind| cx.caught(unwind));
+                        match (caught, &expr.handler) {
+                            (Some(val), Some((Some(param), _))) => cx
+                                .bind(env, param, val)
+                                .and_then(|env| expr.resume_catch(env, cx, r)),
+                            (Some(_), Some((None, _))) => expr.resume_catch(env.clone(), cx, r),
+                            _ => result,
+                        }
+                    }
+                    Some(Frame::Catch(env)) => expr.resume_catch(env, cx, r),
+                    Some(Frame::Finally(result)) => return expr.resume_finally(env, cx, r, result),
+                    Some(frame) => mismatch(frame),
+                };
+                // suspending isn't finishing, so the `finally` waits
+                if let Err(Unwind::Yield(_)) = result {
+                    return result;
+                }
+                expr.resume_finally(env, cx, r, result)
+            }
+            // what's left can't suspend, and `eval` reports any `yield` in it
+            _ => self.eval_kind(env, cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::tests::eval;
+
+    /// Run `gen`, a generator function, with `drive`, code using `it`, a call
+    /// of it, checking the value it gives against the javascript `expected`.
+    fn check(gen: &str, drive: &str, expected: &str) {
+        let src = format!("const gen = {}; const it = gen(); {}", gen, drive);
+        assert_eq!(eval(&src), eval(expected), "running {}", src);
+    }
+
+    #[test]
+    fn yield_in_binary_operands() {
+        check(
+            "function*() { return (yield 1) + (yield 2); }",
+            "[it.next().value, it.next(10).value, it.next(20)]",
+            "[1, 2, { value: 30, done: true }]",
+        );
+    }
+
+    #[test]
+    fn yield_in_conditional() {
+        check(
+            "function*() { return (yield 'test') ? yield 'cons' : yield 'altr'; }",
+            "[it.next().value, it.next(false).value, it.next(7)]",
+            "['test', 'altr', { value: 7, done: true }]",
+        );
+    }
+
+    #[test]
+    fn yield_in_object_literal() {
+        check(
+            "function*() { return { a: yield 1, [yield 2]: yield 3 }; }",
+            "[it.next().value, it.next('x').value, it.next('k').value, it.next('y').value]",
+            "[1, 2, 3, { a: 'x', k: 'y' }]",
+        );
+    }
+
+    #[test]
+    fn yield_in_compound_assignment() {
+        // the old value is read before the `yield` suspends
+        check(
+            "function*() { let s = 1; s += yield s; s *= yield s; return s; }",
+            "[it.next().value, it.next(2).value, it.next(4).value]",
+            "[1, 3, 12]",
+        );
+    }
+
+    #[test]
+    fn yield_in_loop_test_and_update() {
+        check(
+            "function*() {
+                let n = 0;
+                for (let i = 0; i < (yield i); i += yield 'step') { n++; }
+                return n;
+            }",
+            "[it.next().value, it.next(3).value, it.next(2).value,
+              it.next(3).value, it.next(5).value, it.next(0)]",
+            "[0, 'step', 2, 'step', 7, { value: 2, done: true }]",
+        );
+    }
+
+    #[test]
+    fn yield_in_loop_keeps_iteration_bindings() {
+        check(
+            "function*() {
+                const fs = [];
+                for (let i = 0; i < 3; i++) { fs[i] = () => i; yield i; }
+                return fs.map(f => f());
+            }",
+            "[it.next().value, it.next().value, it.next().value, it.next().value]",
+            "[0, 1, 2, [0, 1, 2]]",
+        );
+    }
+
+    #[test]
+    fn yield_in_try_catch_finally() {
+        check(
+            "function*() {
+                try { yield 1; throw yield 2; }
+                catch (e) { yield e; }
+                finally { yield 'finally'; }
+                return 'end';
+            }",
+            "[it.next().value, it.next().value, it.next('boom').value,
+              it.next().value, it.next()]",
+            "[1, 2, 'boom', 'finally', { value: 'end', done: true }]",
+        );
+    }
+
+    #[test]
+    fn finally_keeps_how_the_try_finished() {
+        check(
+            "function*() { try { return 'early'; } finally { yield 'finally'; } }",
+            "[it.next().value, it.next(), it.next()]",
+            "['finally', { value: 'early', done: true }, { value: undefined, done: true }]",
+        );
+    }
+
+    #[test]
+    fn yield_star_hands_on_sent_values() {
+        check(
+            "function*() {
+                const inner = function*() { const a = yield 1; return a * 2; };
+                const r = yield* inner();
+                yield r;
+            }",
+            "[it.next().value, it.next(5).value, it.next()]",
+            "[1, 10, { value: undefined, done: true }]",
+        );
+    }
+
+    #[test]
+    fn yield_star_over_array() {
+        check(
+            "function*() { const r = yield* [1, 2]; return r; }",
+            "[it.next().value, it.next().value, it.next()]",
+            "[1, 2, { value: undefined, done: true }]",
+        );
+    }
+
+    #[test]
+    fn leaving_for_of_closes_generator() {
+        check(
+            "function*() { try { yield 1; yield 2; } finally { x = 9; } }",
+            "let x = 0; for (const v of it) { break; } [x, it.next()]",
+            "[9, { value: undefined, done: true }]",
+        );
+    }
+
+    #[test]
+    fn closing_closes_delegate() {
+        check(
+            "function*() {
+                const inner = function*() { try { yield 1; } finally { log[1] = 'inner'; } };
+                try { yield* inner(); } finally { log[2] = 'outer'; }
+            }",
+            "const log = [0]; it.next(); for (const v of it) { break; } log",
+            "[0, 'inner', 'outer']",
+        );
+    }
+
+    #[test]
+    fn next_while_running_is_an_error() {
+        assert_eq!(
+            eval("const gen = function*() { yield it.next(); }; const it = gen(); it.next()"),
+            "error: generator resumed while already running"
+        );
+    }
+}
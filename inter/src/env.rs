@@ -0,0 +1,221 @@
+use std::rc::Rc;
+
+use crate::store::Loc;
+
+/// A persistent environment, stored as a chain of single-binding frames,
+/// each giving the location in the `Store` that holds the binding's value.
+///
+/// Extending an environment pushes a new frame on the front and shares the
+/// rest of the chain, so it never copies the bindings that came before it.
+/// Closures hold on to the chain they were created in.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Environ {
+    head: Option<Rc<Frame>>,
+}
+
+/// What a name is bound to.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Binding {
+    pub loc: Loc,
+    /// Whether the binding is `const`, and can't be assigned to.
+    pub constant: bool,
+}
+
+#[derive(Debug)]
+struct Frame {
+    ident: String,
+    binding: Binding,
+    next: Option<Rc<Frame>>,
+}
+
+impl Environ {
+    pub fn empty() -> Self {
+        Environ { head: None }
+    }
+
+    /// Bind `ident` in front of `self`, shadowing any outer binding.
+    pub fn extend(&self, ident: String, binding: Binding) -> Self {
+        Environ {
+            head: Some(Rc::new(Frame {
+                ident,
+                binding,
+                next: self.head.clone(),
+            })),
+        }
+    }
+
+    /// Whether `self` and `other` are the very same chain of frames.
+    pub(crate) fn is(&self, other: &Environ) -> bool {
+        match (&self.head, &other.head) {
+            (Some(l), Some(r)) => Rc::ptr_eq(l, r),
+            (l, r) => l.is_none() && r.is_none(),
+        }
+    }
+
+    /// The innermost binding of `ident`.
+    pub fn lookup(&self, ident: &str) -> Option<Binding> {
+        self.frames()
+            .find(|frame| frame.ident == ident)
+            .map(|frame| frame.binding)
+    }
+
+    /// Number of frames between the front of the environment and the
+    /// binding for `ident`, `0` being the most recent binding.
+    // the evaluator resolves names by walking the chain itself, but this is
+    // part of what an environment offers
+    #[allow(dead_code)]
+    pub fn lookup_depth(&self, ident: &str) -> Option<usize> {
+        self.frames().position(|frame| frame.ident == ident)
+    }
+
+    /// The locations bound by the frames `self` has in front of `outer`, an
+    /// environment it was built on.
+    pub fn locs_before(&self, outer: &Environ) -> Vec<Loc> {
+        self.frames_before(outer)
+            .map(|frame| frame.binding.loc)
+            .collect()
+    }
+
+    /// Whether nothing holds on to the frames `self` has in front of
+    /// `outer` but `self` and the environments in `held`.
+    pub fn held_only_by(&self, outer: &Environ, held: &[&Environ]) -> bool {
+        self.frames_before(outer).all(|frame| {
+            let held_by = held
+                .iter()
+                .filter(|env| {
+                    env.head
+                        .as_ref()
+                        .is_some_and(|head| Rc::ptr_eq(head, frame))
+                })
+                .count();
+            // besides those, only the frame in front of it, or `self`
+            Rc::strong_count(frame) == 1 + held_by
+        })
+    }
+
+    /// The bound name closest to `ident` by edit distance, if any is close
+    /// enough to plausibly be a typo of it.
+    pub fn closest(&self, ident: &str) -> Option<&str> {
+        let max_dist = (ident.chars().count() / 3).max(1);
+        self.frames()
+            .map(|frame| (edit_distance(ident, &frame.ident), frame.ident.as_str()))
+            .filter(|(dist, _)| *dist <= max_dist)
+            .min_by_key(|(dist, _)| *dist)
+            .map(|(_, name)| name)
+    }
+
+    fn frames_before<'a>(&'a self, outer: &'a Environ) -> impl Iterator<Item = &'a Rc<Frame>> {
+        let mut frame = self.head.as_ref();
+        std::iter::from_fn(move || {
+            let current = frame.filter(|frame| {
+                !outer
+                    .head
+                    .as_ref()
+                    .is_some_and(|stop| Rc::ptr_eq(frame, stop))
+            })?;
+            frame = current.next.as_ref();
+            Some(current)
+        })
+    }
+
+    fn frames(&self) -> impl Iterator<Item = &Frame> {
+        let mut frame = self.head.as_deref();
+        std::iter::from_fn(move || {
+            let current = frame?;
+            frame = current.next.as_deref();
+            Some(current)
+        })
+    }
+}
+
+/// Edit distance between `a` and `b`, counted in chars, where swapping two
+/// neighbouring chars is one edit, as it is one typo.
+///
+/// This is the optimal string alignment distance: Levenshtein distance plus
+/// transpositions, without editing a transposed pair again.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut before = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let subst = prev[j] + usize::from(ca != cb);
+            curr[j + 1] = subst.min(prev[j + 1] + 1).min(curr[j] + 1);
+            if i > 0 && j > 0 && *ca == b[j - 1] && a[i - 1] == *cb {
+                curr[j + 1] = curr[j + 1].min(before[j - 1] + 1);
+            }
+        }
+        std::mem::swap(&mut before, &mut prev);
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit_distance, Binding, Environ};
+    use crate::store::Store;
+
+    /// `idents` bound in order, the last one in front.
+    fn env_of(idents: &[&str]) -> Environ {
+        let mut store = Store::default();
+        idents.iter().fold(Environ::empty(), |env, ident| {
+            let binding = Binding {
+                loc: store.alloc(None),
+                constant: false,
+            };
+            env.extend(ident.to_string(), binding)
+        })
+    }
+
+    #[test]
+    fn lookup_depth_counts_from_the_front() {
+        let env = env_of(&["a", "b", "c"]);
+        assert_eq!(env.lookup_depth("c"), Some(0));
+        assert_eq!(env.lookup_depth("a"), Some(2));
+        assert_eq!(env.lookup_depth("d"), None);
+    }
+
+    #[test]
+    fn extending_shadows_without_touching_the_original() {
+        let outer = env_of(&["a", "b"]);
+        let inner = outer.extend(
+            "a".to_owned(),
+            Binding {
+                loc: Store::default().alloc(None),
+                constant: true,
+            },
+        );
+        assert_eq!(inner.lookup_depth("a"), Some(0));
+        assert!(inner.lookup("a").is_some_and(|binding| binding.constant));
+        assert_eq!(outer.lookup_depth("a"), Some(1));
+        assert!(outer.lookup("a").is_some_and(|binding| !binding.constant));
+    }
+
+    #[test]
+    fn transposition_is_one_edit() {
+        assert_eq!(edit_distance("coutn", "count"), 1);
+        assert_eq!(edit_distance("ab", "ba"), 1);
+        assert_eq!(edit_distance("ca", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn closest_suggests_a_typo() {
+        let env = env_of(&["total", "count", "index"]);
+        assert_eq!(env.closest("coutn"), Some("count"));
+        assert_eq!(env.closest("indx"), Some("index"));
+    }
+
+    #[test]
+    fn closest_ignores_unrelated_names() {
+        let env = env_of(&["total", "count", "index"]);
+        assert_eq!(env.closest("zebra"), None);
+        assert_eq!(env.closest("x"), None);
+    }
+}
@@ -0,0 +1,337 @@
+use std::fmt::Display;
+
+use crate::{ast::Value, span::Span};
+
+/// Failure to evaluate a program.
+#[derive(Debug, Clone)]
+pub struct EvalError {
+    pub kind: EvalErrorKind,
+    /// Span of the expression that failed, when the ESTree input had one.
+    pub span: Option<Span>,
+}
+
+impl EvalError {
+    /// Attribute this error to `span`, unless an inner expression already
+    /// claimed it.
+    pub(crate) fn or_at(mut self, span: Option<Span>) -> Self {
+        if self.span.is_none() {
+            self.span = span;
+        }
+        self
+    }
+}
+
+impl From<EvalErrorKind> for EvalError {
+    fn from(kind: EvalErrorKind) -> Self {
+        EvalError { kind, span: None }
+    }
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// Reasons evaluation of a program can fail.
+#[derive(Debug, Clone)]
+pub enum EvalErrorKind {
+    /// `op` was applied to operands it is not defined on.
+    TypeMismatch { op: String, operands: Vec<Value> },
+    /// `op` had a zero divisor, `lhs` being the dividend.
+    DivideByZero { op: String, lhs: Value },
+    /// `op` on `operands` overflowed an int.
+    Overflow { op: String, operands: Vec<Value> },
+    /// A shift in `operands` was by a negative amount, or one past the width
+    /// of an int32.
+    ShiftOutOfRange { op: String, operands: Vec<Value> },
+    /// `ident` is not bound, `suggestion` being the closest bound name.
+    Unbound {
+        ident: String,
+        suggestion: Option<String>,
+    },
+    /// `ident` was read in its temporal dead zone, between the start of its
+    /// scope and its declaration.
+    Uninitialized { ident: String },
+    /// `ident` is a `const` binding, and was assigned to.
+    AssignToConst { ident: String },
+    /// `object` has no property named `property`.
+    NoProperty { object: Value, property: String },
+    /// A `break` or `continue`, `jump`, was not inside a loop or statement
+    /// it could refer to.
+    NoJumpTarget { jump: String },
+    /// An `expected` pattern was matched against `found`, which isn't one.
+    Destructure {
+        expected: &'static str,
+        found: Value,
+    },
+    /// `index` is not an element of an array of `len` elements.
+    OutOfBounds { index: i64, len: usize },
+    /// `reduce` was called on an empty array without an initial value.
+    EmptyReduce,
+    /// `object` can't have properties set, as `property` was.
+    SetProperty { object: Value, property: String },
+    /// A call was made to something other than a function.
+    NotCallable { callee: Value },
+    /// A function taking from `min` to `max` arguments, or any number over
+    /// `min` when there's no `max`, was called with `found`.
+    Arity {
+        min: usize,
+        max: Option<usize>,
+        found: usize,
+    },
+    /// `value` was thrown and never caught.
+    Uncaught { value: Value },
+    /// `for...of` or `yield*` was given `value`, which isn't an array or a
+    /// generator.
+    NotIterable { value: Value },
+    /// A generator's `next` was called from inside its own body.
+    GeneratorRunning,
+    /// A `yield` outside a generator, or somewhere in one it can't suspend
+    /// from, like a default value.
+    MisplacedYield,
+    /// Calls were nested `depth` deep, as deep as they are allowed to go.
+    StackOverflow { depth: usize },
+    /// `op` is recognised but has no meaning for these operands.
+    Unsupported { op: String, operands: Vec<Value> },
+}
+
+impl EvalErrorKind {
+    /// The javascript error this is, like `TypeError`, or `None` for the
+    /// errors javascript finds before running anything.
+    pub(crate) fn js_name(&self) -> Option<&'static str> {
+        use EvalErrorKind::*;
+        Some(match self {
+            Unbound { .. } | Uninitialized { .. } => "ReferenceError",
+            DivideByZero { .. }
+            | Overflow { .. }
+            | ShiftOutOfRange { .. }
+            | OutOfBounds { .. }
+            | StackOverflow { .. } => "RangeError",
+            NoJumpTarget { .. } | MisplacedYield => return None,
+            Uncaught { .. } => "Error",
+            TypeMismatch { .. }
+            | AssignToConst { .. }
+            | NoProperty { .. }
+            | Destructure { .. }
+            | EmptyReduce
+            | SetProperty { .. }
+            | NotCallable { .. }
+            | Arity { .. }
+            | NotIterable { .. }
+            | GeneratorRunning
+            | Unsupported { .. } => "TypeError",
+        })
+    }
+}
+
+impl Display for EvalErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use EvalErrorKind::*;
+        match self {
+            TypeMismatch { op, operands } => {
+                write!(f, "type mismatch in `{}`:", op)?;
+                for operand in operands {
+                    write!(f, " {}", operand)?;
+                }
+                Ok(())
+            }
+            Overflow { op, operands } => {
+                write!(f, "integer overflow in `{}`:", op)?;
+                for operand in operands {
+                    write!(f, " {}", operand)?;
+                }
+                Ok(())
+            }
+            ShiftOutOfRange { op, operands } => {
+                write!(f, "shift amount out of range in `{}`:", op)?;
+                for operand in operands {
+                    write!(f, " {}", operand)?;
+                }
+                Ok(())
+            }
+            DivideByZero { op, lhs } => write!(f, "divide by zero in `{}` of {}", op, lhs),
+            Unbound {
+                ident,
+                suggestion: Some(near),
+            } => write!(
+                f,
+                "unbound identifier `{}`, did you mean `{}`?",
+                ident, near
+            ),
+            Unbound {
+                ident,
+                suggestion: None,
+            } => write!(f, "unbound identifier `{}`", ident),
+            Uninitialized { ident } => {
+                write!(f, "`{}` read before its declaration was evaluated", ident)
+            }
+            AssignToConst { ident } => write!(f, "assignment to constant `{}`", ident),
+            NoProperty { object, property } => {
+                write!(f, "no property `{}` on {}", property, object)
+            }
+            Destructure { expected, found } => {
+                write!(
+                    f,
+                    "cannot destructure {} with an {} pattern",
+                    found, expected
+                )
+            }
+            OutOfBounds { index, len } => write!(
+                f,
+                "index {} out of bounds for array of length {}",
+                index, len
+            ),
+            EmptyReduce => write!(f, "reduce of empty array with no initial value"),
+            SetProperty { object, property } => {
+                write!(f, "cannot set property `{}` on {}", property, object)
+            }
+            NoJumpTarget { jump } => write!(f, "`{}` has no loop or label to go to", jump),
+            NotCallable { callee } => write!(f, "call of non function {}", callee),
+            Arity { min, max, found } => {
+                write!(f, "wrong number of arguments in call: expected ")?;
+                match max {
+                    Some(max) if max == min => write!(f, "{}", min)?,
+                    Some(max) => write!(f, "{} to {}", min, max)?,
+                    None => write!(f, "at least {}", min)?,
+                }
+                write!(f, ", found {}", found)
+            }
+            Uncaught { value } => write!(f, "uncaught exception {}", value),
+            NotIterable { value } => write!(f, "{} is not iterable", value),
+            GeneratorRunning => write!(f, "generator resumed while already running"),
+            MisplacedYield => write!(f, "`yield` can't suspend evaluation here"),
+            StackOverflow { depth } => write!(f, "stack overflow: calls nested {} deep", depth),
+            Unsupported { op, operands } => {
+                write!(f, "unsupported `{}`:", op)?;
+                for operand in operands {
+                    write!(f, " {}", operand)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Failure to build an AST out of ESTree JSON.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    /// Path of the offending node from the program root, e.g. `body[0].expression`.
+    pub path: String,
+    /// The ESTree `type` of the offending node, when it has one.
+    pub node: Option<String>,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum ParseErrorKind {
+    /// A required field is absent or `null`.
+    Missing(&'static str),
+    /// A field is present but not of the expected JSON shape.
+    Malformed(&'static str),
+    /// The node `type` is valid ESTree but not supported here.
+    UnsupportedNode,
+    /// The operator is valid JavaScript but not supported here.
+    UnsupportedOperator(String),
+    /// The literal's value has no `Value` counterpart.
+    UnsupportedLiteral,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use ParseErrorKind::*;
+        match &self.kind {
+            Missing(field) => write!(f, "missing field `{}`", field)?,
+            Malformed(field) => write!(f, "malformed field `{}`", field)?,
+            UnsupportedNode => write!(f, "unsupported node")?,
+            UnsupportedOperator(op) => write!(f, "unsupported operator `{}`", op)?,
+            UnsupportedLiteral => write!(f, "unsupported literal")?,
+        }
+        match &self.node {
+            Some(node) => write!(f, " in {} at {}", node, self.path),
+            None => write!(f, " at {}", self.path),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Malformed JavaScript source, found by the built-in parser.
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    /// Byte offset into the source.
+    pub offset: usize,
+    /// 1-based line of `offset`.
+    pub line: usize,
+    /// 1-based column of `offset`, counted in chars.
+    pub column: usize,
+    pub msg: String,
+}
+
+impl SyntaxError {
+    pub(crate) fn new(source: &str, offset: usize, msg: impl Into<String>) -> Self {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        SyntaxError {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            msg: msg.into(),
+        }
+    }
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}:{}", self.msg, self.line, self.column)
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+#[cfg(test)]
+mod tests {
+    use super::{EvalError, EvalErrorKind};
+    use crate::{ast::Value, span::Span};
+
+    #[test]
+    fn inner_span_wins() {
+        let inner = Some(Span { start: 4, end: 5 });
+        let outer = Some(Span { start: 0, end: 9 });
+        let err = EvalError::from(EvalErrorKind::EmptyReduce)
+            .or_at(inner)
+            .or_at(outer);
+        assert_eq!(err.span, inner);
+    }
+
+    #[test]
+    fn display_names_the_operator_and_operands() {
+        let err = EvalErrorKind::TypeMismatch {
+            op: "-".to_owned(),
+            operands: vec![Value::Int(1), Value::Bool(true)],
+        };
+        assert_eq!(
+            err.to_string(),
+            "type mismatch in `-`: (value (number 1)) (value (boolean true))"
+        );
+    }
+
+    #[test]
+    fn display_arity_ranges() {
+        let arity = |min, max| EvalErrorKind::Arity { min, max, found: 0 }.to_string();
+        assert_eq!(
+            arity(1, Some(1)),
+            "wrong number of arguments in call: expected 1, found 0"
+        );
+        assert_eq!(
+            arity(1, Some(3)),
+            "wrong number of arguments in call: expected 1 to 3, found 0"
+        );
+        assert_eq!(
+            arity(2, None),
+            "wrong number of arguments in call: expected at least 2, found 0"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{
+    error::{ParseError, ParseErrorKind},
+    span::Span,
+};
+
+/// A node of ESTree JSON together with its path from the program root,
+/// e.g. `body[0].expression.left`, so construction errors can say where
+/// in the input they happened.
+#[derive(Debug, Clone)]
+pub struct Node<'j> {
+    json: &'j serde_json::Value,
+    path: String,
+}
+
+impl<'j> Node<'j> {
+    pub fn root(json: &'j serde_json::Value, path: &str) -> Self {
+        Node {
+            json,
+            path: path.to_owned(),
+        }
+    }
+
+    pub fn json(&self) -> &'j serde_json::Value {
+        self.json
+    }
+
+    /// The source span of this node, if the ESTree input has `start` and `end`.
+    pub fn span(&self) -> Option<Span> {
+        let start = self.json.get("start")?.as_u64()?;
+        let end = self.json.get("end")?.as_u64()?;
+        Some(Span {
+            start: start as usize,
+            end: end as usize,
+        })
+    }
+
+    /// The ESTree `type` of this node.
+    pub fn node_type(&self) -> Result<&'j str, ParseError> {
+        match self.json.get("type") {
+            Some(serde_json::Value::String(t)) => Ok(t),
+            Some(_) => Err(self.error(ParseErrorKind::Malformed("type"))),
+            None => Err(self.error(ParseErrorKind::Missing("type"))),
+        }
+    }
+
+    /// The child node at `key`, which must be present and not `null`.
+    pub fn get(&self, key: &'static str) -> Result<Node<'j>, ParseError> {
+        self.get_opt(key)
+            .ok_or_else(|| self.error(ParseErrorKind::Missing(key)))
+    }
+
+    /// The child node at `key`, or `None` if it is absent or `null`.
+    pub fn get_opt(&self, key: &'static str) -> Option<Node<'j>> {
+        match self.json.get(key) {
+            None | Some(serde_json::Value::Null) => None,
+            Some(json) => Some(Node {
+                json,
+                path: self.child_path(key),
+            }),
+        }
+    }
+
+    pub fn str(&self, key: &'static str) -> Result<&'j str, ParseError> {
+        self.get(key)?
+            .json
+            .as_str()
+            .ok_or_else(|| self.error(ParseErrorKind::Malformed(key)))
+    }
+
+    /// The elements of the array at `key`.
+    pub fn array(&self, key: &'static str) -> Result<Vec<Node<'j>>, ParseError> {
+        self.get(key)?.elems()
+    }
+
+    /// The elements of this node, which must be an array.
+    pub fn elems(&self) -> Result<Vec<Node<'j>>, ParseError> {
+        let elems = self.json.as_array().ok_or_else(|| ParseError {
+            path: self.path.clone(),
+            node: None,
+            kind: ParseErrorKind::Malformed("array"),
+        })?;
+
+        Ok(elems
+            .iter()
+            .enumerate()
+            .map(|(i, json)| Node {
+                json,
+                path: format!("{}[{}]", self.path, i),
+            })
+            .collect())
+    }
+
+    /// An error about this node, tagged with its path and `type`.
+    pub fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            path: self.path.clone(),
+            node: self
+                .json
+                .get("type")
+                .and_then(|t| t.as_str())
+                .map(str::to_owned),
+            kind,
+        }
+    }
+
+    fn child_path(&self, key: &str) -> String {
+        if self.path.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{}.{}", self.path, key)
+        }
+    }
+}
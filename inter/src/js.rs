@@ -0,0 +1,318 @@
+use std::rc::Rc;
+
+use crate::{
+    ast::{BinOp, Value},
+    error::{EvalError, EvalErrorKind},
+    num,
+    options::NumberMode,
+};
+
+// Javascript's implicit conversions, and the operators built on them, for
+// `Semantics::Js`.
+//
+// `Value::Unit` is `undefined`, and `Value::Null` is `null`.
+
+/// Javascript's `ToBoolean`.
+pub fn to_boolean(val: &Value) -> bool {
+    match val {
+        Value::Bool(v) => *v,
+        Value::Int(v) => *v != 0,
+        Value::Float(v) => *v != 0. && !v.is_nan(),
+        Value::String(v) => !v.is_empty(),
+        Value::Fn(_) | Value::Array(_) | Value::Object(_) | Value::Generator(_) => true,
+        Value::Null | Value::Unit => false,
+    }
+}
+
+/// Javascript's `ToNumber`, as an int where `mode` keeps ints exact.
+pub fn to_number(val: &Value, mode: NumberMode) -> Value {
+    match val {
+        Value::Int(_) | Value::Float(_) => val.clone(),
+        Value::Bool(v) => Value::Int(*v as i64),
+        Value::Null => Value::Int(0),
+        Value::String(v) => {
+            let n = string_to_number(v);
+            // `-0` has no int to be
+            let integral =
+                n.fract() == 0. && n.abs() < 2f64.powi(53) && n.to_bits() != (-0f64).to_bits();
+            match mode {
+                NumberMode::Exact if integral => Value::Int(n as i64),
+                _ => Value::Float(n),
+            }
+        }
+        // `[]` is 0 and `[5]` 5, by way of their strings
+        Value::Array(_) | Value::Object(_) | Value::Generator(_) => {
+            to_number(&to_primitive(val), mode)
+        }
+        Value::Fn(_) | Value::Unit => Value::Float(f64::NAN),
+    }
+}
+
+/// Javascript's `ToString`.
+pub fn to_string(val: &Value) -> String {
+    to_string_in(val, &mut Vec::new())
+}
+
+/// `to_string`, inside the arrays in `outer`. As with javascript's `join`,
+/// an array inside itself is the empty string.
+fn to_string_in(val: &Value, outer: &mut Vec<*const ()>) -> String {
+    match val {
+        Value::String(v) => v.clone(),
+        Value::Int(v) => v.to_string(),
+        Value::Float(v) => num::fmt_f64(*v),
+        Value::Bool(v) => v.to_string(),
+        // javascript gives the function's source, which isn't kept around
+        Value::Fn(_) => "function".to_owned(),
+        Value::Array(elems) => {
+            let ptr = Rc::as_ptr(elems).cast();
+            if outer.contains(&ptr) {
+                return String::new();
+            }
+            outer.push(ptr);
+            let joined = elems
+                .borrow()
+                .iter()
+                .map(|elem| match elem {
+                    Value::Null | Value::Unit => String::new(),
+                    elem => to_string_in(elem, outer),
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            outer.pop();
+            joined
+        }
+        Value::Object(_) => "[object Object]".to_owned(),
+        Value::Generator(_) => "[object Generator]".to_owned(),
+        Value::Null => "null".to_owned(),
+        Value::Unit => "undefined".to_owned(),
+    }
+}
+
+/// The number a string converts to: its trimmed contents as a decimal,
+/// `0x`, `0o` or `0b` literal, `0` when blank and NaN otherwise.
+fn string_to_number(s: &str) -> f64 {
+    let s = s.trim();
+    if s.is_empty() {
+        return 0.;
+    }
+
+    let radix = match s.get(..2) {
+        Some("0x" | "0X") => 16,
+        Some("0o" | "0O") => 8,
+        Some("0b" | "0B") => 2,
+        _ => 10,
+    };
+    if radix != 10 {
+        let digits = &s[2..];
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return f64::NAN;
+        }
+        return digits.chars().fold(0., |acc, d| {
+            acc * radix as f64 + d.to_digit(radix).unwrap() as f64
+        });
+    }
+
+    let unsigned = s.strip_prefix(['+', '-']).unwrap_or(s);
+    if unsigned == "Infinity" {
+        return if s.starts_with('-') {
+            f64::NEG_INFINITY
+        } else {
+            f64::INFINITY
+        };
+    }
+    // rust also parses `inf` and `nan`, which javascript doesn't
+    if !unsigned
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+    {
+        return f64::NAN;
+    }
+    s.parse().unwrap_or(f64::NAN)
+}
+
+/// Javascript's `ToPrimitive`, which only changes functions, arrays,
+/// objects and generators.
+fn to_primitive(val: &Value) -> Value {
+    match val {
+        Value::Fn(_) | Value::Array(_) | Value::Object(_) | Value::Generator(_) => {
+            Value::String(to_string(val))
+        }
+        _ => val.clone(),
+    }
+}
+
+/// The loose equality of `==`, which converts between numbers, strings and
+/// booleans.
+pub fn loose_eq(lhs: &Value, rhs: &Value) -> bool {
+    use Value::*;
+    match (lhs, rhs) {
+        (Null | Unit, Null | Unit) => true,
+        (Null | Unit, _) | (_, Null | Unit) => false,
+        (
+            Fn(_) | Array(_) | Object(_) | Generator(_),
+            Fn(_) | Array(_) | Object(_) | Generator(_),
+        ) => strict_eq(lhs, rhs),
+        (Fn(_) | Array(_) | Object(_) | Generator(_), _) => loose_eq(&to_primitive(lhs), rhs),
+        (_, Fn(_) | Array(_) | Object(_) | Generator(_)) => loose_eq(lhs, &to_primitive(rhs)),
+        (String(l), String(r)) => l == r,
+        // any other mix of numbers, strings and booleans compares as numbers
+        _ => strict_eq(
+            &to_number(lhs, NumberMode::Double),
+            &to_number(rhs, NumberMode::Double),
+        ),
+    }
+}
+
+/// The strict equality of `===`: equal values of the same type, except NaN.
+pub fn strict_eq(lhs: &Value, rhs: &Value) -> bool {
+    use Value::*;
+    match (lhs, rhs) {
+        (Int(_) | Float(_), Int(_) | Float(_)) => num::compare(&BinOp::Eq, lhs, rhs) == Some(true),
+        (String(l), String(r)) => l == r,
+        (Bool(l), Bool(r)) => l == r,
+        (Fn(l), Fn(r)) => l.is(r),
+        (Array(l), Array(r)) => Rc::ptr_eq(l, r),
+        (Object(l), Object(r)) => Rc::ptr_eq(l, r),
+        (Generator(l), Generator(r)) => Rc::ptr_eq(l, r),
+        (Null, Null) | (Unit, Unit) => true,
+        _ => false,
+    }
+}
+
+/// The relational operators on two strings, comparing them by utf-16 code
+/// unit as javascript does.
+pub fn compare_strings(op: &BinOp, lhs: &str, rhs: &str) -> Option<bool> {
+    let ord = lhs.encode_utf16().cmp(rhs.encode_utf16());
+    Some(match op {
+        BinOp::Eq | BinOp::StrictEq => ord.is_eq(),
+        BinOp::Ne | BinOp::StrictNe => ord.is_ne(),
+        BinOp::Lt => ord.is_lt(),
+        BinOp::Le => ord.is_le(),
+        BinOp::Gt => ord.is_gt(),
+        BinOp::Ge => ord.is_ge(),
+        _ => return None,
+    })
+}
+
+/// Any binary operator other than `&&` and `||`, converting its operands
+/// the way javascript does.
+pub fn binary(op: &BinOp, lhs: &Value, rhs: &Value, mode: NumberMode) -> Result<Value, EvalError> {
+    use BinOp::*;
+    match op {
+        Eq => return Ok(Value::Bool(loose_eq(lhs, rhs))),
+        Ne => return Ok(Value::Bool(!loose_eq(lhs, rhs))),
+        StrictEq => return Ok(Value::Bool(strict_eq(lhs, rhs))),
+        StrictNe => return Ok(Value::Bool(!strict_eq(lhs, rhs))),
+        _ => {}
+    }
+
+    let (lhs, rhs) = (to_primitive(lhs), to_primitive(rhs));
+    match (op, &lhs, &rhs) {
+        // `+` concatenates as soon as either side is a string
+        (Add, Value::String(_), _) | (Add, _, Value::String(_)) => {
+            return Ok(Value::String(to_string(&lhs) + &to_string(&rhs)));
+        }
+        (_, Value::String(l), Value::String(r)) => {
+            if let Some(result) = compare_strings(op, l, r) {
+                return Ok(Value::Bool(result));
+            }
+        }
+        _ => {}
+    }
+
+    let l = to_number(&lhs, mode);
+    let r = to_number(&rhs, mode);
+    let result = match op {
+        Add | Sub | Mul | Div | Rem => num::arith(op, &l, &r, mode),
+        Lt | Le | Gt | Ge => num::compare(op, &l, &r).map(|result| Ok(Value::Bool(result))),
+        BitXor | BitAnd | BitOr | Shl | Shr | UShr => num::bitwise(op, &l, &r, mode),
+        _ => None,
+    };
+
+    result.unwrap_or_else(|| {
+        Err(EvalErrorKind::Unsupported {
+            op: op.to_string(),
+            operands: vec![lhs, rhs],
+        }
+        .into())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::{binary, loose_eq, strict_eq, to_boolean, to_number};
+    use crate::{
+        ast::{BinOp, Value},
+        options::NumberMode::{Double, Exact},
+    };
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_owned())
+    }
+
+    #[test]
+    fn falsy_values() {
+        for val in [
+            Value::Int(0),
+            Value::Float(f64::NAN),
+            string(""),
+            Value::Null,
+            Value::Unit,
+        ] {
+            assert!(!to_boolean(&val), "{} is falsy", val);
+        }
+        let empty = Value::Array(Rc::new(RefCell::new(Vec::new())));
+        assert!(to_boolean(&empty));
+        assert!(to_boolean(&string("0")));
+    }
+
+    #[test]
+    fn strings_to_numbers() {
+        let number = |s: &str| to_number(&string(s), Exact).to_string();
+        assert_eq!(number(" 42\n"), "(value (number 42))");
+        assert_eq!(number(""), "(value (number 0))");
+        assert_eq!(number("0x1f"), "(value (number 31))");
+        assert_eq!(number("1.5e1"), "(value (number 15))");
+        assert_eq!(number("-Infinity"), "(value (number -Infinity))");
+        assert_eq!(number("inf"), "(value (number NaN))");
+        assert_eq!(number("12px"), "(value (number NaN))");
+        assert!(matches!(to_number(&string("7"), Double), Value::Float(n) if n == 7.));
+        assert!(matches!(
+            to_number(&Value::Bool(true), Exact),
+            Value::Int(1)
+        ));
+        assert!(matches!(to_number(&Value::Unit, Exact), Value::Float(n) if n.is_nan()));
+    }
+
+    #[test]
+    fn loose_and_strict_equality() {
+        assert!(loose_eq(&string("1"), &Value::Int(1)));
+        assert!(!strict_eq(&string("1"), &Value::Int(1)));
+        assert!(loose_eq(&Value::Bool(true), &Value::Float(1.)));
+        assert!(loose_eq(&Value::Null, &Value::Unit));
+        assert!(!strict_eq(&Value::Null, &Value::Unit));
+        assert!(!loose_eq(&Value::Null, &Value::Int(0)));
+        assert!(!loose_eq(&Value::Float(f64::NAN), &Value::Float(f64::NAN)));
+        assert!(strict_eq(&Value::Int(1), &Value::Float(1.)));
+
+        let arr = Value::Array(Rc::new(RefCell::new(vec![Value::Int(5)])));
+        assert!(loose_eq(&arr, &string("5")));
+        assert!(strict_eq(&arr, &arr.clone()));
+        let other = Value::Array(Rc::new(RefCell::new(vec![Value::Int(5)])));
+        assert!(!loose_eq(&arr, &other));
+    }
+
+    #[test]
+    fn plus_concatenates_strings() {
+        let add = |lhs, rhs| {
+            binary(&BinOp::Add, &lhs, &rhs, Exact)
+                .expect("`+` is defined on anything")
+                .to_string()
+        };
+        assert_eq!(add(string("a"), Value::Int(1)), string("a1").to_string());
+        assert_eq!(add(Value::Null, Value::Bool(true)), "(value (number 1))");
+        assert_eq!(add(Value::Unit, Value::Int(1)), "(value (number NaN))");
+    }
+}
@@ -0,0 +1,485 @@
+use crate::error::SyntaxError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tok {
+    /// A numeric literal, with its value.
+    Num(f64),
+    /// A string literal, with escapes already processed.
+    Str(String),
+    /// An identifier or keyword.
+    Ident(String),
+    /// An operator or punctuation.
+    Punct(&'static str),
+    /// A piece of a template literal, running from its opening `` ` `` or
+    /// the `}` ending a substitution, to the next `${` or its closing
+    /// `` ` ``, which `tail` says.
+    Template {
+        cooked: String,
+        raw: String,
+        tail: bool,
+    },
+    Eof,
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub tok: Tok,
+    /// Byte offset of the first character of the token.
+    pub start: usize,
+    /// Byte offset just past the last character of the token.
+    pub end: usize,
+    /// Whether a line break separates this token from the one before it,
+    /// for automatic semicolon insertion.
+    pub nl_before: bool,
+}
+
+// longest first, so the lexer can take the first that matches
+const PUNCTS: &[&str] = &[
+    ">>>=", "...", "===", "!==", "**=", "<<=", ">>=", ">>>", "=>", "==", "!=", "<=", ">=", "&&",
+    "||", "??", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "<<", ">>", "**", "?.",
+    "{", "}", "(", ")", "[", "]", ";", ",", "<", ">", "+", "-", "*", "/", "%", "&", "|", "^", "!",
+    "~", "?", ":", "=", ".",
+];
+
+/// Split `source` into tokens, ending with a `Tok::Eof`.
+pub fn tokenize(source: &str) -> Result<Vec<Token>, SyntaxError> {
+    let mut lexer = Lexer {
+        src: source,
+        pos: 0,
+        braces: Vec::new(),
+    };
+    let mut tokens = Vec::new();
+
+    loop {
+        let nl_before = lexer.skip_trivia()?;
+        let start = lexer.pos;
+        let tok = lexer.next_tok()?;
+        let done = tok == Tok::Eof;
+        tokens.push(Token {
+            tok,
+            start,
+            end: lexer.pos,
+            nl_before,
+        });
+        if done {
+            return Ok(tokens);
+        }
+    }
+}
+
+struct Lexer<'s> {
+    src: &'s str,
+    pos: usize,
+    /// For each template substitution being lexed, how many `{` inside it
+    /// are still open, so the `}` ending it can be told apart.
+    braces: Vec<usize>,
+}
+
+impl Lexer<'_> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<char> {
+        self.src[self.pos..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn error(&self, offset: usize, msg: impl Into<String>) -> SyntaxError {
+        SyntaxError::new(self.src, offset, msg)
+    }
+
+    /// Skip whitespace and comments, returning whether they held a line break.
+    fn skip_trivia(&mut self) -> Result<bool, SyntaxError> {
+        let mut nl = false;
+        loop {
+            match self.peek() {
+                Some('\n') | Some('\r') | Some('\u{2028}') | Some('\u{2029}') => {
+                    nl = true;
+                    self.bump();
+                }
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('/') if self.peek_at(1) == Some('/') => {
+                    while !matches!(self.peek(), None | Some('\n') | Some('\r')) {
+                        self.bump();
+                    }
+                }
+                Some('/') if self.peek_at(1) == Some('*') => {
+                    let start = self.pos;
+                    self.pos += 2;
+                    match self.src[self.pos..].find("*/") {
+                        Some(len) => {
+                            nl |= self.src[self.pos..self.pos + len].contains(['\n', '\r']);
+                            self.pos += len + 2;
+                        }
+                        None => return Err(self.error(start, "unterminated comment")),
+                    }
+                }
+                _ => return Ok(nl),
+            }
+        }
+    }
+
+    fn next_tok(&mut self) -> Result<Tok, SyntaxError> {
+        let start = self.pos;
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Ok(Tok::Eof),
+        };
+
+        if c.is_ascii_digit() || (c == '.' && self.peek_at(1).is_some_and(|c| c.is_ascii_digit())) {
+            return self.number();
+        }
+        if c == '"' || c == '\'' {
+            return self.string(c);
+        }
+        if c == '`' || (c == '}' && self.braces.last() == Some(&0)) {
+            if c == '}' {
+                self.braces.pop();
+            }
+            self.bump();
+            return self.template(start);
+        }
+        if is_ident_start(c) {
+            while self.peek().is_some_and(is_ident_part) {
+                self.bump();
+            }
+            return Ok(Tok::Ident(self.src[start..self.pos].to_owned()));
+        }
+
+        let rest = &self.src[self.pos..];
+        match PUNCTS.iter().find(|p| rest.starts_with(*p)) {
+            // `?.` followed by a digit is `?` then a number, as in `a ?.5 : 1`
+            Some(&"?.") if self.peek_at(2).is_some_and(|c| c.is_ascii_digit()) => {
+                self.pos += 1;
+                Ok(Tok::Punct("?"))
+            }
+            Some(p) => {
+                self.pos += p.len();
+                match (*p, self.braces.last_mut()) {
+                    ("{", Some(open)) => *open += 1,
+                    ("}", Some(open)) => *open -= 1,
+                    _ => {}
+                }
+                Ok(Tok::Punct(p))
+            }
+            None => Err(self.error(start, format!("unexpected character `{}`", c))),
+        }
+    }
+
+    fn number(&mut self) -> Result<Tok, SyntaxError> {
+        let start = self.pos;
+        let radix = match (self.peek(), self.peek_at(1)) {
+            (Some('0'), Some('x' | 'X')) => 16,
+            (Some('0'), Some('o' | 'O')) => 8,
+            (Some('0'), Some('b' | 'B')) => 2,
+            _ => 10,
+        };
+
+        let value = if radix != 10 {
+            self.pos += 2;
+            let digits_start = self.pos;
+            while self.peek().is_some_and(|c| c.is_digit(radix) || c == '_') {
+                self.bump();
+            }
+            let digits = self.src[digits_start..self.pos].replace('_', "");
+            if digits.is_empty() {
+                return Err(self.error(start, "expected digits after radix prefix"));
+            }
+            digits.chars().fold(0f64, |acc, d| {
+                acc * radix as f64 + d.to_digit(radix).unwrap() as f64
+            })
+        } else {
+            self.digits();
+            if self.peek() == Some('.') {
+                self.bump();
+                self.digits();
+            }
+            if matches!(self.peek(), Some('e' | 'E')) {
+                self.bump();
+                if matches!(self.peek(), Some('+' | '-')) {
+                    self.bump();
+                }
+                if !self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    return Err(self.error(start, "expected exponent digits"));
+                }
+                self.digits();
+            }
+            let text = self.src[start..self.pos].replace('_', "");
+            text.parse::<f64>()
+                .map_err(|_| self.error(start, format!("invalid number `{}`", text)))?
+        };
+
+        if self.peek().is_some_and(is_ident_start) {
+            return Err(self.error(self.pos, "identifier directly after number"));
+        }
+        Ok(Tok::Num(value))
+    }
+
+    fn digits(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '_') {
+            self.bump();
+        }
+    }
+
+    fn string(&mut self, quote: char) -> Result<Tok, SyntaxError> {
+        let start = self.pos;
+        self.bump();
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some(c) if c == quote => return Ok(Tok::Str(value)),
+                Some('\\') => {
+                    if let Some(c) = self.escape()? {
+                        value.push(c);
+                    }
+                }
+                Some('\n') | Some('\r') | None => {
+                    return Err(self.error(start, "unterminated string"));
+                }
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    /// The rest of a template piece starting at `start`, just after its
+    /// `` ` `` or `}`.
+    fn template(&mut self, start: usize) -> Result<Tok, SyntaxError> {
+        let mut cooked = String::new();
+        let mut raw = String::new();
+        loop {
+            let piece_start = self.pos;
+            let tail = match self.bump() {
+                Some('`') => true,
+                Some('$') if self.peek() == Some('{') => {
+                    self.bump();
+                    self.braces.push(0);
+                    false
+                }
+                Some('\\') => {
+                    if let Some(c) = self.escape()? {
+                        cooked.push(c);
+                    }
+                    raw.push_str(&self.src[piece_start..self.pos]);
+                    continue;
+                }
+                // line breaks in templates are always `\n`
+                Some('\r') => {
+                    if self.peek() == Some('\n') {
+                        self.bump();
+                    }
+                    cooked.push('\n');
+                    raw.push('\n');
+                    continue;
+                }
+                Some(c) => {
+                    cooked.push(c);
+                    raw.push(c);
+                    continue;
+                }
+                None => return Err(self.error(start, "unterminated template")),
+            };
+            return Ok(Tok::Template { cooked, raw, tail });
+        }
+    }
+
+    /// The character an escape sequence stands for, just after its `\`, or
+    /// `None` for a line continuation.
+    fn escape(&mut self) -> Result<Option<char>, SyntaxError> {
+        let start = self.pos - 1;
+        let c = match self.bump() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('v') => '\u{b}',
+            Some('0') if !self.peek().is_some_and(|c| c.is_ascii_digit()) => '\0',
+            Some('x') => self.hex_escape(start, 2)?,
+            Some('u') if self.peek() == Some('{') => {
+                self.bump();
+                let len = self.src[self.pos..].find('}').unwrap_or(0);
+                let c = self.hex_escape(start, len)?;
+                self.bump();
+                c
+            }
+            Some('u') => self.hex_escape(start, 4)?,
+            Some('\r') => {
+                if self.peek() == Some('\n') {
+                    self.bump();
+                }
+                return Ok(None);
+            }
+            Some('\n') | Some('\u{2028}') | Some('\u{2029}') => return Ok(None),
+            Some(c) if c.is_ascii_digit() => {
+                return Err(self.error(start, "octal escapes are not supported"))
+            }
+            Some(c) => c,
+            None => return Err(self.error(start, "unterminated string")),
+        };
+        Ok(Some(c))
+    }
+
+    fn hex_escape(&mut self, start: usize, len: usize) -> Result<char, SyntaxError> {
+        let digits = self.src[self.pos..].get(..len).unwrap_or("");
+        let c = (len > 0 && digits.chars().all(|c| c.is_ascii_hexdigit()))
+            .then(|| u32::from_str_radix(digits, 16).ok())
+            .flatten()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error(start, "invalid escape sequence"))?;
+        self.pos += len;
+        Ok(c)
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c == '_' || c == '$' || c.is_alphabetic()
+}
+
+fn is_ident_part(c: char) -> bool {
+    is_ident_start(c) || c.is_alphanumeric()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, Tok};
+
+    fn toks(source: &str) -> Vec<Tok> {
+        tokenize(source)
+            .expect("test source lexes")
+            .into_iter()
+            .map(|token| token.tok)
+            .collect()
+    }
+
+    fn ident(name: &str) -> Tok {
+        Tok::Ident(name.to_owned())
+    }
+
+    #[test]
+    fn longest_punct_wins() {
+        assert_eq!(
+            toks("a >>>= b === c => ..."),
+            [
+                ident("a"),
+                Tok::Punct(">>>="),
+                ident("b"),
+                Tok::Punct("==="),
+                ident("c"),
+                Tok::Punct("=>"),
+                Tok::Punct("..."),
+                Tok::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn optional_chain_before_digit_is_conditional() {
+        assert_eq!(
+            toks("a?.5:1"),
+            [
+                ident("a"),
+                Tok::Punct("?"),
+                Tok::Num(0.5),
+                Tok::Punct(":"),
+                Tok::Num(1.),
+                Tok::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(
+            toks("0x1F 0o17 0b101 1_000 1.5e3 .25"),
+            [
+                Tok::Num(31.),
+                Tok::Num(15.),
+                Tok::Num(5.),
+                Tok::Num(1000.),
+                Tok::Num(1500.),
+                Tok::Num(0.25),
+                Tok::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn string_escapes() {
+        assert_eq!(
+            toks(r#"'a\'b' "\n\t\x41B\u{1F600}""#),
+            [
+                Tok::Str("a'b".to_owned()),
+                Tok::Str("\n\tAB\u{1F600}".to_owned()),
+                Tok::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn template_pieces() {
+        let template = |cooked: &str, raw: &str, tail| Tok::Template {
+            cooked: cooked.to_owned(),
+            raw: raw.to_owned(),
+            tail,
+        };
+        assert_eq!(
+            toks(r"`a\n${ {x: 1} }b${c}`"),
+            [
+                template("a\n", r"a\n", false),
+                Tok::Punct("{"),
+                ident("x"),
+                Tok::Punct(":"),
+                Tok::Num(1.),
+                Tok::Punct("}"),
+                template("b", "b", false),
+                ident("c"),
+                template("", "", true),
+                Tok::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn line_breaks_before_tokens() {
+        let nl: Vec<_> = tokenize("a /* \n */ b // c\nd e")
+            .expect("test source lexes")
+            .into_iter()
+            .map(|token| token.nl_before)
+            .collect();
+        assert_eq!(nl, [false, true, true, false, false]);
+    }
+
+    #[test]
+    fn token_offsets_are_bytes() {
+        let spans: Vec<_> = tokenize("'é' + x")
+            .expect("test source lexes")
+            .into_iter()
+            .map(|token| (token.start, token.end))
+            .collect();
+        assert_eq!(spans, [(0, 4), (5, 6), (7, 8), (8, 8)]);
+    }
+
+    #[test]
+    fn error_positions() {
+        for (source, line, column, msg) in [
+            ("x = 'abc", 1, 5, "unterminated string"),
+            ("a\n  /* b", 2, 3, "unterminated comment"),
+            ("a\nb # c", 2, 3, "unexpected character `#`"),
+            ("0x", 1, 1, "expected digits after radix prefix"),
+        ] {
+            let err = tokenize(source).expect_err(source);
+            assert_eq!(
+                (err.line, err.column, err.msg.as_str()),
+                (line, column, msg)
+            );
+        }
+    }
+}
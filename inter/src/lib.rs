@@ -1,3 +1,17 @@
 mod ast;
+mod env;
+mod error;
+mod estree;
+mod js;
+mod lexer;
+mod num;
+mod options;
+mod parser;
+mod span;
+mod store;
 
-pub use crate::ast::{Program, Value};
+pub use crate::ast::{quote, FnValue, Generator, ObjectValue, Program, Session, Value};
+pub use crate::error::{EvalError, EvalErrorKind, ParseError, ParseErrorKind, SyntaxError};
+pub use crate::options::{NumberMode, Options, Semantics};
+pub use crate::parser::parse;
+pub use crate::span::Span;
@@ -0,0 +1,315 @@
+use inter::{EvalError, EvalErrorKind, NumberMode, Program, Semantics, Session, Span, Value};
+use std::{
+    env, fs,
+    io::{self, BufRead, Write},
+    process::{self, Command, Stdio},
+    thread,
+};
+
+const USAGE: &str = "\
+usage: inter <command> [options]
+
+commands:
+    parse       print the program's ast
+    eval        evaluate the program and print its value
+    check       check that the program is well formed, without running it
+    repl        read, evaluate and print lines of javascript
+
+options:
+    --js                parse the input as javascript with the built-in parser,
+                        instead of reading estree json
+    --exec              run `acorn --ecma2024` on the input instead of reading estree json,
+                        `repl` uses the built-in parser unless given this
+    --input FILE        read input from FILE instead of stdin, except for `repl`
+    --format sexp|json  output format, defaults to sexp
+    --numbers exact|double
+                        keep ints exact, or make every number a double as javascript
+                        does, defaults to exact
+    --semantics strict|js
+                        reject operands of the wrong type, or convert them as
+                        javascript does, defaults to strict
+    --curry             curry functions of several parameters
+";
+
+// exit codes
+const EXIT_EVAL: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_INPUT: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cmd {
+    Parse,
+    Eval,
+    Check,
+    Repl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Sexp,
+    Json,
+}
+
+/// Where the estree json for a program comes from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Source {
+    /// The input is already estree json.
+    Estree,
+    /// The input is javascript, parsed with the built-in parser.
+    Native,
+    /// The input is javascript, parsed by running `acorn`.
+    Acorn,
+}
+
+#[derive(Debug)]
+struct Options {
+    cmd: Cmd,
+    source: Source,
+    input: Option<String>,
+    format: Format,
+    eval: inter::Options,
+}
+
+impl Options {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let cmd = match args.next().as_deref() {
+            Some("parse") => Cmd::Parse,
+            Some("eval") => Cmd::Eval,
+            Some("check") => Cmd::Check,
+            Some("repl") => Cmd::Repl,
+            Some(other) => return Err(format!("unknown command `{}`", other)),
+            None => return Err("missing command".to_owned()),
+        };
+
+        let mut opts = Options {
+            cmd,
+            source: if cmd == Cmd::Repl {
+                Source::Native
+            } else {
+                Source::Estree
+            },
+            input: None,
+            format: Format::Sexp,
+            eval: inter::Options::default(),
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--js" => opts.source = Source::Native,
+                "--curry" => opts.eval.curry = true,
+                "--exec" => opts.source = Source::Acorn,
+                "--input" => match args.next() {
+                    Some(file) => opts.input = Some(file),
+                    None => return Err("`--input` expects a file".to_owned()),
+                },
+                "--format" => match args.next().as_deref() {
+                    Some("sexp") => opts.format = Format::Sexp,
+                    Some("json") => opts.format = Format::Json,
+                    _ => return Err("`--format` expects `sexp` or `json`".to_owned()),
+                },
+                "--numbers" => match args.next().as_deref() {
+                    Some("exact") => opts.eval.numbers = NumberMode::Exact,
+                    Some("double") => opts.eval.numbers = NumberMode::Double,
+                    _ => return Err("`--numbers` expects `exact` or `double`".to_owned()),
+                },
+                "--semantics" => match args.next().as_deref() {
+                    Some("strict") => opts.eval.semantics = Semantics::Strict,
+                    Some("js") => opts.eval.semantics = Semantics::Js,
+                    _ => return Err("`--semantics` expects `strict` or `js`".to_owned()),
+                },
+                _ => return Err(format!("unknown option `{}`", arg)),
+            }
+        }
+        if cmd == Cmd::Repl && opts.input.is_some() {
+            return Err("`repl` reads lines from stdin, and takes no `--input`".to_owned());
+        }
+
+        Ok(opts)
+    }
+}
+
+fn main() {
+    let opts = match Options::from_args(env::args().skip(1)) {
+        Ok(opts) => opts,
+        Err(err) => {
+            eprintln!("inter: {}\n\n{}", err, USAGE);
+            process::exit(EXIT_USAGE);
+        }
+    };
+
+    if opts.cmd == Cmd::Repl {
+        repl(&opts);
+        return;
+    }
+
+    let text = match read_input(&opts) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("inter: {}", err);
+            process::exit(EXIT_INPUT);
+        }
+    };
+    let (json, program) = match to_estree(&text, opts.source).and_then(|json| {
+        Program::from_estree(&json)
+            .map(|program| (json, program))
+            .map_err(|err| format!("unable to create ast: {}", err))
+    }) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("inter: {}", err);
+            process::exit(EXIT_INPUT);
+        }
+    };
+    let js = (opts.source != Source::Estree).then_some(text.as_str());
+
+    match opts.cmd {
+        Cmd::Parse => match opts.format {
+            Format::Sexp => println!("{}", program),
+            Format::Json => println!("{:#}", json),
+        },
+        Cmd::Eval => {
+            if !print_eval(program.eval_with(opts.eval), &opts, js) {
+                process::exit(EXIT_EVAL);
+            }
+        }
+        Cmd::Check => {}
+        Cmd::Repl => unreachable!(),
+    }
+}
+
+/// Print `result`, the result of evaluating a program, returning whether it
+/// succeeded.
+///
+/// Errors are also rendered against `js`, the program's source, on stderr
+/// when it is known.
+fn print_eval(result: Result<Value, EvalError>, opts: &Options, js: Option<&str>) -> bool {
+    if let (Err(err), Some(js)) = (&result, js) {
+        if let Some(span) = err.span {
+            // acorn counts offsets in utf-16 code units
+            let span = match opts.source {
+                Source::Acorn => span.from_utf16(js),
+                _ => span,
+            };
+            eprintln!("{}", span.render(js, &err.to_string()));
+        }
+    }
+    let uncaught = match &result {
+        Err(err) => match &err.kind {
+            EvalErrorKind::Uncaught { value } => Some(value),
+            _ => None,
+        },
+        Ok(_) => None,
+    };
+    match (&result, uncaught, opts.format) {
+        (Ok(val), _, Format::Sexp) => println!("{}", val),
+        (Ok(val), _, Format::Json) => println!("{}", val.to_json()),
+        // a thrown value is printed as a value, not as a message
+        (Err(_), Some(value), Format::Sexp) => println!("(error (uncaught {}))", value),
+        (Err(_), Some(value), Format::Json) => println!(
+            "{}",
+            serde_json::json!({ "error": { "uncaught": value.to_json() } })
+        ),
+        (Err(err), None, Format::Sexp) => println!("(error {})", inter::quote(&err.to_string())),
+        (Err(err), None, Format::Json) => {
+            println!("{}", serde_json::json!({ "error": err.to_string() }))
+        }
+    }
+    result.is_ok()
+}
+
+/// Read the input named by `opts`.
+fn read_input(opts: &Options) -> Result<String, String> {
+    match &opts.input {
+        Some(file) => fs::read_to_string(file).map_err(|err| format!("reading {}: {}", file, err)),
+        None => io::read_to_string(io::stdin()).map_err(|err| format!("reading stdin: {}", err)),
+    }
+}
+
+/// Turn `text` into estree json, according to where `source` says it came
+/// from.
+fn to_estree(text: &str, source: Source) -> Result<serde_json::Value, String> {
+    match source {
+        Source::Estree => {
+            serde_json::from_str(text).map_err(|err| format!("parsing json input: {}", err))
+        }
+        Source::Native => inter::parse(text).map_err(|err| {
+            let span = Span {
+                start: err.offset,
+                end: err.offset + 1,
+            };
+            format!("syntax error: {}\n{}", err, span.snippet(text))
+        }),
+        Source::Acorn => acorn(text).and_then(|json| {
+            serde_json::from_str(&json).map_err(|err| format!("parsing acorn output: {}", err))
+        }),
+    }
+}
+
+/// Run `acorn --ecma2024` over `source`, returning the estree json it prints.
+fn acorn(source: &str) -> Result<String, String> {
+    let mut child = Command::new("acorn")
+        .arg("--ecma2024")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("exec acorn: {}", err))?;
+
+    // written from another thread, as acorn may fill the pipe to its stdout
+    // before it has read all of its stdin
+    let mut stdin = child.stdin.take().expect("acorn stdin is piped");
+    let (written, output) = thread::scope(|scope| {
+        let writer = scope.spawn(move || stdin.write_all(source.as_bytes()));
+        let output = child.wait_with_output();
+        (writer.join().expect("writing to acorn"), output)
+    });
+
+    let output = output.map_err(|err| format!("exec acorn: {}", err))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+    written.map_err(|err| format!("writing to acorn: {}", err))?;
+
+    String::from_utf8(output.stdout).map_err(|err| format!("reading acorn output: {}", err))
+}
+
+/// Evaluate each line of stdin in the scope of the declarations entered
+/// before it.
+fn repl(opts: &Options) {
+    let mut session = Session::new(opts.eval);
+    let stdin = io::stdin();
+
+    prompt();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                eprintln!("inter: reading stdin: {}", err);
+                process::exit(EXIT_INPUT);
+            }
+        };
+
+        if !line.trim().is_empty() {
+            match repl_line(&line, opts.source) {
+                Ok(program) => {
+                    let js = (opts.source != Source::Estree).then_some(line.as_str());
+                    print_eval(session.eval(&program), opts, js);
+                }
+                Err(err) => eprintln!("inter: {}", err),
+            }
+        }
+        prompt();
+    }
+    println!();
+}
+
+/// Build a program out of `line`.
+fn repl_line(line: &str, source: Source) -> Result<Program, String> {
+    let json = to_estree(line, source)?;
+    Program::from_estree(&json).map_err(|err| format!("unable to create ast: {}", err))
+}
+
+fn prompt() {
+    print!("> ");
+    io::stdout().flush().expect("flushing stdout");
+}
@@ -0,0 +1,351 @@
+use crate::{
+    ast::{BinOp, Value},
+    error::{EvalError, EvalErrorKind},
+    options::NumberMode,
+};
+
+/// The arithmetic operators, `+ - * / %`, on two numbers.
+///
+/// Returns `None` when either operand is not a number.
+pub fn arith(
+    op: &BinOp,
+    lhs: &Value,
+    rhs: &Value,
+    mode: NumberMode,
+) -> Option<Result<Value, EvalError>> {
+    use Value::*;
+    Some(match (mode, lhs, rhs) {
+        (NumberMode::Exact, Int(l), Int(r)) => int_arith(op, *l, *r),
+        _ => float_arith(op, as_f64(lhs)?, as_f64(rhs)?, mode)?.map(Float),
+    })
+}
+
+fn int_arith(op: &BinOp, l: i64, r: i64) -> Result<Value, EvalError> {
+    let operands = || vec![Value::Int(l), Value::Int(r)];
+    if r == 0 && matches!(op, BinOp::Div | BinOp::Rem) {
+        return Err(EvalErrorKind::DivideByZero {
+            op: op.to_string(),
+            lhs: Value::Int(l),
+        }
+        .into());
+    }
+
+    let result = match op {
+        BinOp::Add => l.checked_add(r),
+        BinOp::Sub => l.checked_sub(r),
+        BinOp::Mul => l.checked_mul(r),
+        BinOp::Div if l.checked_rem(r) != Some(0) => {
+            return Ok(Value::Float(l as f64 / r as f64));
+        }
+        BinOp::Div => l.checked_div(r),
+        BinOp::Rem => l.checked_rem(r),
+        _ => {
+            return Err(EvalErrorKind::Unsupported {
+                op: op.to_string(),
+                operands: operands(),
+            }
+            .into())
+        }
+    };
+
+    result.map(Value::Int).ok_or_else(|| {
+        EvalErrorKind::Overflow {
+            op: op.to_string(),
+            operands: operands(),
+        }
+        .into()
+    })
+}
+
+fn float_arith(op: &BinOp, l: f64, r: f64, mode: NumberMode) -> Option<Result<f64, EvalError>> {
+    if r == 0. && mode == NumberMode::Exact && matches!(op, BinOp::Div | BinOp::Rem) {
+        return Some(Err(EvalErrorKind::DivideByZero {
+            op: op.to_string(),
+            lhs: Value::Float(l),
+        }
+        .into()));
+    }
+
+    Some(Ok(match op {
+        BinOp::Add => l + r,
+        BinOp::Sub => l - r,
+        BinOp::Mul => l * r,
+        BinOp::Div => l / r,
+        // rust's `%` is fmod, which is what javascript's is too
+        BinOp::Rem => l % r,
+        _ => return None,
+    }))
+}
+
+/// The relational operators, `< <= > >= == != === !==`, on two numbers.
+///
+/// Returns `None` when either operand is not a number.
+pub fn compare(op: &BinOp, lhs: &Value, rhs: &Value) -> Option<bool> {
+    use Value::*;
+    let ord = match (lhs, rhs) {
+        (Int(l), Int(r)) => Some(l.cmp(r)),
+        _ => as_f64(lhs)?.partial_cmp(&as_f64(rhs)?),
+    };
+
+    // `ord` is `None` when comparing against NaN, where only `!=` holds
+    Some(match op {
+        BinOp::Lt => ord.is_some_and(|o| o.is_lt()),
+        BinOp::Le => ord.is_some_and(|o| o.is_le()),
+        BinOp::Gt => ord.is_some_and(|o| o.is_gt()),
+        BinOp::Ge => ord.is_some_and(|o| o.is_ge()),
+        BinOp::Eq | BinOp::StrictEq => ord.is_some_and(|o| o.is_eq()),
+        BinOp::Ne | BinOp::StrictNe => !ord.is_some_and(|o| o.is_eq()),
+        _ => return None,
+    })
+}
+
+/// The bitwise operators, `^ & | << >> >>>`, on two numbers.
+///
+/// As in javascript, the operands are truncated to 32 bit ints first, and
+/// the result is one too. `NumberMode::Double` masks shift amounts to their
+/// low five bits like javascript does, where `NumberMode::Exact` rejects
+/// amounts outside `0..32`.
+///
+/// Returns `None` when either operand is not a number.
+pub fn bitwise(
+    op: &BinOp,
+    lhs: &Value,
+    rhs: &Value,
+    mode: NumberMode,
+) -> Option<Result<Value, EvalError>> {
+    let l = to_int32(lhs)?;
+    let r = to_int32(rhs)?;
+
+    let shift = || -> Result<u32, EvalError> {
+        let amount = r as u32;
+        match mode {
+            NumberMode::Double => Ok(amount & 31),
+            NumberMode::Exact if (0..32).contains(&r) => Ok(amount),
+            NumberMode::Exact => Err(EvalErrorKind::ShiftOutOfRange {
+                op: op.to_string(),
+                operands: vec![lhs.clone(), rhs.clone()],
+            }
+            .into()),
+        }
+    };
+
+    let result = match op {
+        BinOp::BitXor => (l ^ r) as f64,
+        BinOp::BitAnd => (l & r) as f64,
+        BinOp::BitOr => (l | r) as f64,
+        BinOp::Shl => match shift() {
+            Ok(amount) => (l << amount) as f64,
+            Err(err) => return Some(Err(err)),
+        },
+        BinOp::Shr => match shift() {
+            Ok(amount) => (l >> amount) as f64,
+            Err(err) => return Some(Err(err)),
+        },
+        // the one operator whose result is unsigned
+        BinOp::UShr => match shift() {
+            Ok(amount) => ((l as u32) >> amount) as f64,
+            Err(err) => return Some(Err(err)),
+        },
+        _ => return None,
+    };
+
+    Some(Ok(from_int(result, mode)))
+}
+
+/// Unary `~`, on a number truncated to a 32 bit int.
+pub fn bit_not(val: &Value, mode: NumberMode) -> Option<Value> {
+    Some(from_int(!to_int32(val)? as f64, mode))
+}
+
+/// The number javascript's `ToInt32` makes of `val`: truncated, then
+/// wrapped into the range of an `i32`.
+fn to_int32(val: &Value) -> Option<i32> {
+    Some(match val {
+        // `as` on ints of different sizes wraps
+        Value::Int(v) => *v as i32,
+        Value::Float(v) if !v.is_finite() => 0,
+        Value::Float(v) => v.trunc().rem_euclid(4294967296.) as u32 as i32,
+        _ => return None,
+    })
+}
+
+/// An integral `n` as a number in `mode`.
+pub fn from_int(n: f64, mode: NumberMode) -> Value {
+    match mode {
+        NumberMode::Exact => Value::Int(n as i64),
+        NumberMode::Double => Value::Float(n),
+    }
+}
+
+/// Unary minus.
+pub fn negate(val: &Value, mode: NumberMode) -> Option<Result<Value, EvalError>> {
+    Some(match (mode, val) {
+        (NumberMode::Exact, Value::Int(v)) => v.checked_neg().map(Value::Int).ok_or_else(|| {
+            EvalErrorKind::Overflow {
+                op: "-".to_owned(),
+                operands: vec![val.clone()],
+            }
+            .into()
+        }),
+        _ => Ok(Value::Float(-as_f64(val)?)),
+    })
+}
+
+pub fn as_f64(val: &Value) -> Option<f64> {
+    match val {
+        Value::Int(v) => Some(*v as f64),
+        Value::Float(v) => Some(*v),
+        _ => None,
+    }
+}
+
+/// Format `n` the way javascript's `Number.prototype.toString` does.
+pub fn fmt_f64(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".to_owned()
+    } else if n.is_infinite() {
+        if n > 0. { "Infinity" } else { "-Infinity" }.to_owned()
+    } else if n == 0. {
+        // includes -0
+        "0".to_owned()
+    } else if n.abs() >= 1e21 || n.abs() < 1e-6 {
+        // rust writes `1e21` and `1e-7` where javascript has `1e+21`
+        let exp = format!("{:e}", n);
+        match exp.split_once('e') {
+            Some((mantissa, e)) if !e.starts_with('-') => format!("{}e+{}", mantissa, e),
+            _ => exp,
+        }
+    } else {
+        n.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{arith, bitwise, to_int32};
+    use crate::{
+        ast::{BinOp, Value},
+        options::NumberMode::{self, Double, Exact},
+    };
+
+    /// The printed result of `op` on `lhs` and `rhs`, or its error.
+    fn arith_str(op: BinOp, lhs: Value, rhs: Value, mode: NumberMode) -> String {
+        match arith(&op, &lhs, &rhs, mode).expect("operands are numbers") {
+            Ok(val) => val.to_string(),
+            Err(err) => format!("error: {}", err),
+        }
+    }
+
+    #[test]
+    fn ints_stay_exact_until_mixed_with_floats() {
+        use Value::{Float, Int};
+        assert!(matches!(
+            arith(&BinOp::Add, &Int(1), &Int(2), Exact),
+            Some(Ok(Int(3)))
+        ));
+        assert!(matches!(
+            arith(&BinOp::Add, &Int(1), &Float(2.5), Exact),
+            Some(Ok(Float(n))) if n == 3.5
+        ));
+        assert!(matches!(
+            arith(&BinOp::Div, &Int(6), &Int(2), Exact),
+            Some(Ok(Int(3)))
+        ));
+        // a remainder makes the quotient a float
+        assert!(matches!(
+            arith(&BinOp::Div, &Int(7), &Int(2), Exact),
+            Some(Ok(Float(n))) if n == 3.5
+        ));
+        assert!(matches!(
+            arith(&BinOp::Add, &Int(1), &Int(2), Double),
+            Some(Ok(Float(n))) if n == 3.
+        ));
+        assert!(arith(&BinOp::Add, &Int(1), &Value::Unit, Exact).is_none());
+    }
+
+    #[test]
+    fn exact_ints_overflow_where_doubles_round() {
+        assert_eq!(
+            arith_str(BinOp::Add, Value::Int(i64::MAX), Value::Int(1), Exact),
+            format!(
+                "error: integer overflow in `+`: (value (number {})) (value (number 1))",
+                i64::MAX
+            )
+        );
+        assert_eq!(
+            arith_str(BinOp::Add, Value::Int(i64::MAX), Value::Int(1), Double),
+            "(value (number 9223372036854776000))"
+        );
+    }
+
+    #[test]
+    fn dividing_by_zero() {
+        assert_eq!(
+            arith_str(BinOp::Rem, Value::Int(1), Value::Int(0), Exact),
+            "error: divide by zero in `%` of (value (number 1))"
+        );
+        assert_eq!(
+            arith_str(BinOp::Div, Value::Float(1.), Value::Int(0), Exact),
+            "error: divide by zero in `/` of (value (number 1))"
+        );
+        assert_eq!(
+            arith_str(BinOp::Div, Value::Int(-1), Value::Int(0), Double),
+            "(value (number -Infinity))"
+        );
+    }
+
+    fn bitwise_str(op: BinOp, lhs: Value, rhs: Value, mode: NumberMode) -> String {
+        match bitwise(&op, &lhs, &rhs, mode).expect("operands are numbers") {
+            Ok(val) => val.to_string(),
+            Err(err) => format!("error: {}", err),
+        }
+    }
+
+    #[test]
+    fn to_int32_truncates_and_wraps() {
+        assert_eq!(to_int32(&Value::Float(-1.9)), Some(-1));
+        assert_eq!(to_int32(&Value::Float(2147483648.)), Some(i32::MIN));
+        assert_eq!(to_int32(&Value::Float(4294967301.)), Some(5));
+        assert_eq!(to_int32(&Value::Int(1 << 32)), Some(0));
+        assert_eq!(to_int32(&Value::Float(f64::NAN)), Some(0));
+        assert_eq!(to_int32(&Value::Float(f64::NEG_INFINITY)), Some(0));
+        assert_eq!(to_int32(&Value::Unit), None);
+    }
+
+    #[test]
+    fn unsigned_shift_is_unsigned() {
+        assert_eq!(
+            bitwise_str(BinOp::UShr, Value::Int(-1), Value::Int(0), Exact),
+            "(value (number 4294967295))"
+        );
+        assert_eq!(
+            bitwise_str(BinOp::UShr, Value::Int(-1), Value::Int(28), Double),
+            "(value (number 15))"
+        );
+        assert_eq!(
+            bitwise_str(BinOp::Shr, Value::Int(-16), Value::Int(2), Exact),
+            "(value (number -4))"
+        );
+    }
+
+    #[test]
+    fn shift_amounts_outside_an_int32() {
+        assert_eq!(
+            bitwise_str(BinOp::Shl, Value::Int(1), Value::Int(32), Exact),
+            "error: shift amount out of range in `<<`: (value (number 1)) (value (number 32))"
+        );
+        assert_eq!(
+            bitwise_str(BinOp::Shr, Value::Int(1), Value::Int(-1), Exact),
+            "error: shift amount out of range in `>>`: (value (number 1)) (value (number -1))"
+        );
+        // javascript only looks at the low five bits
+        assert_eq!(
+            bitwise_str(BinOp::Shl, Value::Int(1), Value::Int(33), Double),
+            "(value (number 2))"
+        );
+        assert_eq!(
+            bitwise_str(BinOp::Shl, Value::Int(1), Value::Int(-1), Double),
+            "(value (number -2147483648))"
+        );
+    }
+}
@@ -0,0 +1,42 @@
+/// Knobs for how a program is evaluated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    pub numbers: NumberMode,
+    pub semantics: Semantics,
+    /// Whether functions of several parameters are curried: a call with too
+    /// few arguments returns the function with those applied, and a call
+    /// with too many calls its result with the rest, so `f(a, b)` and
+    /// `f(a)(b)` are the same.
+    pub curry: bool,
+}
+
+/// How `Value::Int` and `Value::Float` arithmetic behaves.
+///
+/// In both modes an operation mixing an int and a float promotes the int,
+/// and comparisons work across the two.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NumberMode {
+    /// Ints stay ints where the result is exact. Overflow and division by
+    /// zero are errors, and division only leaves the ints when it has a
+    /// remainder.
+    #[default]
+    Exact,
+    /// Every number is an IEEE-754 double, as in JavaScript. Nothing
+    /// overflows, and division by zero gives `Infinity` or `NaN`.
+    Double,
+}
+
+/// How operators treat values of the wrong type.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Semantics {
+    /// Operators only take the types they are meant for, so `!`, `&&`,
+    /// `||` and `?:` want booleans and arithmetic wants numbers. Anything
+    /// else is a type mismatch, and `===` is the same as `==`.
+    #[default]
+    Strict,
+    /// Values are converted as in JavaScript: `ToBoolean` for conditions,
+    /// `ToNumber` for arithmetic, `+` concatenating when either side is a
+    /// string, `==` converting where `===` doesn't, and `&&` and `||`
+    /// giving back one of their operands.
+    Js,
+}
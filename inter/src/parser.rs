@@ -0,0 +1,1423 @@
+use serde_json::{json, Map, Value as Json};
+
+use crate::{
+    ast::grow_stack,
+    error::SyntaxError,
+    lexer::{tokenize, Tok, Token},
+};
+
+/// Parse JavaScript `source` into the same ESTree json `acorn --ecma2024`
+/// gives for it, so it can go through `Program::from_estree` unchanged.
+///
+/// Only the subset of the language the interpreter understands is
+/// supported. `start` and `end` are byte offsets into `source`.
+pub fn parse(source: &str) -> Result<Json, SyntaxError> {
+    let mut parser = Parser {
+        src: source,
+        toks: tokenize(source)?,
+        pos: 0,
+        last_end: 0,
+        in_generator: false,
+        depth: 0,
+    };
+
+    let mut body = Vec::new();
+    while parser.peek().tok != Tok::Eof {
+        body.push(parser.statement()?);
+    }
+
+    let mut program = json!({
+        "type": "Program",
+        "start": 0,
+        "end": source.len(),
+        "body": [],
+        "sourceType": "script",
+    });
+    // moved in, as `json!` would copy it
+    program["body"] = Json::Array(body);
+    Ok(program)
+}
+
+const RESERVED: &[&str] = &[
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "null",
+    "return",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// How deeply statements and expressions can nest, so that neither parsing
+/// nor what's done with the tree after runs out of stack.
+const MAX_DEPTH: usize = 1000;
+
+const ASSIGN_OPS: &[&str] = &[
+    "=", "+=", "-=", "*=", "/=", "%=", "<<=", ">>=", ">>>=", "&=", "|=", "^=",
+];
+
+/// Whether `expr` can be assigned to.
+fn is_target(expr: &Json) -> bool {
+    expr["type"] == "Identifier" || expr["type"] == "MemberExpression"
+}
+
+fn binop_prec(op: &str) -> Option<u8> {
+    Some(match op {
+        "??" => 1,
+        "||" => 2,
+        "&&" => 3,
+        "|" => 4,
+        "^" => 5,
+        "&" => 6,
+        "==" | "!=" | "===" | "!==" => 7,
+        "<" | ">" | "<=" | ">=" | "instanceof" | "in" => 8,
+        "<<" | ">>" | ">>>" => 9,
+        "+" | "-" => 10,
+        "*" | "/" | "%" => 11,
+        "**" => 12,
+        _ => return None,
+    })
+}
+
+struct Parser<'s> {
+    src: &'s str,
+    toks: Vec<Token>,
+    pos: usize,
+    /// End of the last token consumed, which is where a node being built ends.
+    last_end: usize,
+    /// Whether the innermost function is a generator, where `yield` is an
+    /// expression.
+    in_generator: bool,
+    /// How deep in the tree the node being parsed is.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.toks[self.pos]
+    }
+
+    fn peek_at(&self, n: usize) -> &Token {
+        &self.toks[(self.pos + n).min(self.toks.len() - 1)]
+    }
+
+    fn bump(&mut self) -> Token {
+        let tok = self.toks[self.pos].clone();
+        if tok.tok != Tok::Eof {
+            self.pos += 1;
+        }
+        self.last_end = tok.end;
+        tok
+    }
+
+    fn is_punct(&self, p: &str) -> bool {
+        matches!(&self.peek().tok, Tok::Punct(q) if *q == p)
+    }
+
+    fn eat_punct(&mut self, p: &str) -> bool {
+        let is = self.is_punct(p);
+        if is {
+            self.bump();
+        }
+        is
+    }
+
+    fn expect_punct(&mut self, p: &str) -> Result<Token, SyntaxError> {
+        if self.is_punct(p) {
+            Ok(self.bump())
+        } else {
+            Err(self.unexpected(&format!("`{}`", p)))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> SyntaxError {
+        let tok = self.peek();
+        let found = match &tok.tok {
+            Tok::Eof => "end of input".to_owned(),
+            _ => format!("`{}`", &self.src[tok.start..tok.end]),
+        };
+        SyntaxError::new(
+            self.src,
+            tok.start,
+            format!("expected {}, found {}", expected, found),
+        )
+    }
+
+    /// An ESTree node of type `ty` spanning from `start` to the last token
+    /// consumed, with the fields in `fields`.
+    ///
+    /// The fields are moved in rather than copied, which `json!` would do,
+    /// so building a tree costs time in proportion to its size.
+    fn node<const N: usize>(&self, ty: &str, start: usize, fields: [(&str, Json); N]) -> Json {
+        // in the order acorn gives them
+        let mut node = Map::new();
+        node.insert("type".to_owned(), Json::from(ty));
+        node.insert("start".to_owned(), Json::from(start));
+        node.insert("end".to_owned(), Json::from(self.last_end));
+        for (key, val) in fields {
+            node.insert(key.to_owned(), val);
+        }
+        Json::Object(node)
+    }
+
+    /// `f(self)`, parsing a node a level deeper in the tree.
+    fn nested<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, SyntaxError>,
+    ) -> Result<T, SyntaxError> {
+        let depth = self.depth;
+        self.descend()?;
+        let result = grow_stack(|| f(self));
+        self.depth = depth;
+        result
+    }
+
+    /// Go a level deeper in the tree, or fail once that's deeper than
+    /// `MAX_DEPTH`.
+    fn descend(&mut self) -> Result<(), SyntaxError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(SyntaxError::new(
+                self.src,
+                self.peek().start,
+                format!("nested more than {} deep", MAX_DEPTH),
+            ));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// The end of a statement: a `;`, or a place one would be inserted.
+    fn semicolon(&mut self) -> Result<(), SyntaxError> {
+        if self.eat_punct(";") || self.is_punct("}") || self.peek().nl_before {
+            return Ok(());
+        }
+        match self.peek().tok {
+            Tok::Eof => Ok(()),
+            _ => Err(self.unexpected("`;`")),
+        }
+    }
+
+    fn statement(&mut self) -> Result<Json, SyntaxError> {
+        self.nested(Self::statement_kind)
+    }
+
+    fn statement_kind(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.peek().start;
+        match &self.peek().tok {
+            Tok::Ident(kw) if kw == "let" || kw == "const" || kw == "var" => {
+                let decl = self.var_decl()?;
+                self.semicolon()?;
+                Ok(self.with_end(decl))
+            }
+            Tok::Ident(kw) if kw == "return" => {
+                self.bump();
+                let argument = if self.is_punct(";")
+                    || self.is_punct("}")
+                    || self.peek().nl_before
+                    || self.peek().tok == Tok::Eof
+                {
+                    Json::Null
+                } else {
+                    self.expression()?
+                };
+                self.semicolon()?;
+                Ok(self.node("ReturnStatement", start, [("argument", argument)]))
+            }
+            Tok::Ident(kw) if kw == "if" => {
+                self.bump();
+                let test = self.paren_expression()?;
+                let consequent = self.statement()?;
+                let alternate = if matches!(&self.peek().tok, Tok::Ident(kw) if kw == "else") {
+                    self.bump();
+                    self.statement()?
+                } else {
+                    Json::Null
+                };
+                Ok(self.node(
+                    "IfStatement",
+                    start,
+                    [
+                        ("test", test),
+                        ("consequent", consequent),
+                        ("alternate", alternate),
+                    ],
+                ))
+            }
+            Tok::Ident(kw) if kw == "while" => {
+                self.bump();
+                let test = self.paren_expression()?;
+                let body = self.statement()?;
+                Ok(self.node("WhileStatement", start, [("test", test), ("body", body)]))
+            }
+            Tok::Ident(kw) if kw == "do" => {
+                self.bump();
+                let body = self.statement()?;
+                if !matches!(&self.peek().tok, Tok::Ident(kw) if kw == "while") {
+                    return Err(self.unexpected("`while`"));
+                }
+                self.bump();
+                let test = self.paren_expression()?;
+                // a `;` is always inserted after `do ... while (...)`
+                self.eat_punct(";");
+                Ok(self.node("DoWhileStatement", start, [("body", body), ("test", test)]))
+            }
+            Tok::Ident(kw) if kw == "for" => self.for_statement(),
+            Tok::Ident(kw) if kw == "throw" => {
+                self.bump();
+                if self.peek().nl_before {
+                    return Err(self.unexpected("an expression on the same line as `throw`"));
+                }
+                let argument = self.expression()?;
+                self.semicolon()?;
+                Ok(self.node("ThrowStatement", start, [("argument", argument)]))
+            }
+            Tok::Ident(kw) if kw == "try" => self.try_statement(),
+            Tok::Ident(kw) if kw == "function" => self.function(true),
+            Tok::Ident(kw) if kw == "break" || kw == "continue" => {
+                let ty = match kw.as_str() {
+                    "break" => "BreakStatement",
+                    _ => "ContinueStatement",
+                };
+                self.bump();
+                let label = match self.peek().tok {
+                    Tok::Ident(_) if !self.peek().nl_before => self.ident()?,
+                    _ => Json::Null,
+                };
+                self.semicolon()?;
+                Ok(self.node(ty, start, [("label", label)]))
+            }
+            Tok::Ident(name)
+                if !RESERVED.contains(&name.as_str()) && self.peek_at(1).tok == Tok::Punct(":") =>
+            {
+                let label = self.ident()?;
+                self.bump();
+                let body = self.statement()?;
+                Ok(self.node(
+                    "LabeledStatement",
+                    start,
+                    [("label", label), ("body", body)],
+                ))
+            }
+            Tok::Punct("{") => self.block(),
+            Tok::Punct(";") => {
+                self.bump();
+                Ok(self.node("EmptyStatement", start, []))
+            }
+            _ => {
+                let expression = self.expression()?;
+                self.semicolon()?;
+                Ok(self.node("ExpressionStatement", start, [("expression", expression)]))
+            }
+        }
+    }
+
+    /// `node` extended to end at the last token consumed, for statements
+    /// whose trailing `;` is part of them.
+    fn with_end(&self, mut node: Json) -> Json {
+        node["end"] = json!(self.last_end);
+        node
+    }
+
+    fn var_decl(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.peek().start;
+        let kind = match self.bump().tok {
+            Tok::Ident(kind) => kind,
+            _ => unreachable!("var_decl called on a non keyword"),
+        };
+
+        let mut declarations = Vec::new();
+        loop {
+            let dec_start = self.peek().start;
+            let id = self.binding()?;
+            let init = if self.eat_punct("=") {
+                self.assign()?
+            } else if kind == "const" {
+                return Err(self.unexpected("`=` in const declaration"));
+            } else if id["type"] != "Identifier" {
+                return Err(self.unexpected("`=` after a destructuring pattern"));
+            } else {
+                Json::Null
+            };
+            declarations.push(self.node(
+                "VariableDeclarator",
+                dec_start,
+                [("id", id), ("init", init)],
+            ));
+
+            if !self.eat_punct(",") {
+                break;
+            }
+        }
+
+        Ok(self.node(
+            "VariableDeclaration",
+            start,
+            [("declarations", declarations.into()), ("kind", kind.into())],
+        ))
+    }
+
+    /// `for (init; test; update) body`, any of the three parts being
+    /// optional.
+    fn for_statement(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.bump().start;
+        self.expect_punct("(")?;
+
+        let init = match &self.peek().tok {
+            Tok::Punct(";") => Json::Null,
+            Tok::Ident(kw) if kw == "let" || kw == "const" || kw == "var" => {
+                // `for (let x of ...)` has a declaration without an
+                // initializer, which `var_decl` won't take, so read the
+                // pattern and back up if `of` doesn't follow
+                let pos = self.pos;
+                let decl_start = self.peek().start;
+                let kind = kw.clone();
+                self.bump();
+                let id = self.binding()?;
+                if self.is_keyword("of") {
+                    let declarator = self.node(
+                        "VariableDeclarator",
+                        id["start"].as_u64().unwrap_or_default() as usize,
+                        [("id", id), ("init", Json::Null)],
+                    );
+                    let left = self.node(
+                        "VariableDeclaration",
+                        decl_start,
+                        [
+                            ("declarations", Json::Array(vec![declarator])),
+                            ("kind", kind.into()),
+                        ],
+                    );
+                    return self.for_of(start, left);
+                }
+                self.pos = pos;
+                self.var_decl()?
+            }
+            _ => {
+                let init = self.expression()?;
+                if self.is_keyword("of") {
+                    if !is_target(&init) {
+                        return Err(SyntaxError::new(
+                            self.src,
+                            init["start"].as_u64().unwrap_or_default() as usize,
+                            "invalid `for...of` target",
+                        ));
+                    }
+                    return self.for_of(start, init);
+                }
+                init
+            }
+        };
+        self.expect_punct(";")?;
+        let test = match self.is_punct(";") {
+            true => Json::Null,
+            false => self.expression()?,
+        };
+        self.expect_punct(";")?;
+        let update = match self.is_punct(")") {
+            true => Json::Null,
+            false => self.expression()?,
+        };
+        self.expect_punct(")")?;
+        let body = self.statement()?;
+
+        Ok(self.node(
+            "ForStatement",
+            start,
+            [
+                ("init", init),
+                ("test", test),
+                ("update", update),
+                ("body", body),
+            ],
+        ))
+    }
+
+    /// The rest of `for (left of right) body`, from the `of`.
+    fn for_of(&mut self, start: usize, left: Json) -> Result<Json, SyntaxError> {
+        self.bump();
+        let right = self.assign()?;
+        self.expect_punct(")")?;
+        let body = self.statement()?;
+
+        Ok(self.node(
+            "ForOfStatement",
+            start,
+            [
+                ("await", Json::Bool(false)),
+                ("left", left),
+                ("right", right),
+                ("body", body),
+            ],
+        ))
+    }
+
+    /// `try` and its block, then a `catch`, a `finally` or both.
+    fn try_statement(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.bump().start;
+        let block = self.block()?;
+
+        let handler = if self.is_keyword("catch") {
+            let handler_start = self.bump().start;
+            // the binding is optional, as in `catch { ... }`
+            let param = if self.eat_punct("(") {
+                let param = self.binding()?;
+                self.expect_punct(")")?;
+                param
+            } else {
+                Json::Null
+            };
+            let body = self.block()?;
+            self.node(
+                "CatchClause",
+                handler_start,
+                [("param", param), ("body", body)],
+            )
+        } else {
+            Json::Null
+        };
+        let finalizer = if self.is_keyword("finally") {
+            self.bump();
+            self.block()?
+        } else if handler.is_null() {
+            return Err(self.unexpected("`catch` or `finally`"));
+        } else {
+            Json::Null
+        };
+
+        Ok(self.node(
+            "TryStatement",
+            start,
+            [
+                ("block", block),
+                ("handler", handler),
+                ("finalizer", finalizer),
+            ],
+        ))
+    }
+
+    fn is_keyword(&self, kw: &str) -> bool {
+        matches!(&self.peek().tok, Tok::Ident(name) if name == kw)
+    }
+
+    /// A parenthesised expression, as in `while (...)`.
+    fn paren_expression(&mut self) -> Result<Json, SyntaxError> {
+        self.expect_punct("(")?;
+        let expr = self.expression()?;
+        self.expect_punct(")")?;
+        Ok(expr)
+    }
+
+    fn block(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.expect_punct("{")?.start;
+        let mut body = Vec::new();
+        while !self.is_punct("}") {
+            if self.peek().tok == Tok::Eof {
+                return Err(self.unexpected("`}`"));
+            }
+            body.push(self.statement()?);
+        }
+        self.bump();
+        Ok(self.node("BlockStatement", start, [("body", body.into())]))
+    }
+
+    fn ident(&mut self) -> Result<Json, SyntaxError> {
+        match &self.peek().tok {
+            Tok::Ident(name) if !RESERVED.contains(&name.as_str()) => {
+                let name = name.clone();
+                let start = self.bump().start;
+                Ok(self.node("Identifier", start, [("name", name.into())]))
+            }
+            _ => Err(self.unexpected("an identifier")),
+        }
+    }
+
+    /// The name after a `.`, which unlike an identifier can be a reserved
+    /// word.
+    fn property_name(&mut self) -> Result<Json, SyntaxError> {
+        match &self.peek().tok {
+            Tok::Ident(name) => {
+                let name = name.clone();
+                let start = self.bump().start;
+                Ok(self.node("Identifier", start, [("name", name.into())]))
+            }
+            _ => Err(self.unexpected("a property name")),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Json, SyntaxError> {
+        self.assign()
+    }
+
+    fn assign(&mut self) -> Result<Json, SyntaxError> {
+        self.nested(Self::assign_kind)
+    }
+
+    fn assign_kind(&mut self) -> Result<Json, SyntaxError> {
+        if self.arrow_ahead() {
+            return self.arrow();
+        }
+        if self.in_generator && self.is_keyword("yield") {
+            return self.yield_expression();
+        }
+
+        let start = self.peek().start;
+        let left = self.conditional()?;
+        let op = match &self.peek().tok {
+            Tok::Punct(op) if ASSIGN_OPS.contains(op) => op.to_string(),
+            _ => return Ok(left),
+        };
+        if !is_target(&left) {
+            return Err(SyntaxError::new(
+                self.src,
+                start,
+                "invalid assignment target",
+            ));
+        }
+        self.bump();
+
+        // assignment is right associative
+        let right = self.assign()?;
+        Ok(self.node(
+            "AssignmentExpression",
+            start,
+            [("operator", op.into()), ("left", left), ("right", right)],
+        ))
+    }
+
+    /// `yield`, `yield expr` or `yield* expr`.
+    fn yield_expression(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.bump().start;
+        let delegate = !self.peek().nl_before && self.eat_punct("*");
+        // a bare `yield` ends at whatever ends the expression it's in
+        let tok = self.peek();
+        let ends = match tok.tok {
+            Tok::Eof | Tok::Punct(")" | "]" | "}" | "," | ";" | ":") => true,
+            // the rest of a template after a substitution
+            Tok::Template { .. } => !self.src[tok.start..].starts_with('`'),
+            _ => false,
+        };
+        let bare = !delegate && (tok.nl_before || ends);
+        let argument = if bare { Json::Null } else { self.assign()? };
+
+        Ok(self.node(
+            "YieldExpression",
+            start,
+            [("delegate", delegate.into()), ("argument", argument)],
+        ))
+    }
+
+    fn conditional(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.peek().start;
+        let test = self.binary(0)?;
+        if !self.eat_punct("?") {
+            return Ok(test);
+        }
+
+        let consequent = self.assign()?;
+        self.expect_punct(":")?;
+        let alternate = self.assign()?;
+        Ok(self.node(
+            "ConditionalExpression",
+            start,
+            [
+                ("test", test),
+                ("consequent", consequent),
+                ("alternate", alternate),
+            ],
+        ))
+    }
+
+    /// Binary and logical operators binding at least as tight as `min_prec`.
+    fn binary(&mut self, min_prec: u8) -> Result<Json, SyntaxError> {
+        let start = self.peek().start;
+        let mut left = self.unary()?;
+
+        // each operator puts `left` a level deeper
+        let depth = self.depth;
+        loop {
+            let op = match &self.peek().tok {
+                Tok::Punct(op) => op.to_string(),
+                Tok::Ident(op) if op == "instanceof" || op == "in" => op.clone(),
+                _ => break,
+            };
+            let prec = match binop_prec(&op) {
+                Some(prec) if prec >= min_prec => prec,
+                _ => break,
+            };
+            self.bump();
+
+            // `**` is the one right associative operator
+            let right = if op == "**" {
+                self.binary(prec)?
+            } else {
+                self.binary(prec + 1)?
+            };
+            let ty = match op.as_str() {
+                "&&" | "||" | "??" => "LogicalExpression",
+                _ => "BinaryExpression",
+            };
+            left = self.node(
+                ty,
+                start,
+                [("left", left), ("operator", op.into()), ("right", right)],
+            );
+            self.descend()?;
+        }
+        self.depth = depth;
+
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.peek().start;
+        if let Tok::Punct(op @ ("++" | "--")) = self.peek().tok {
+            self.bump();
+            let argument = self.nested(Self::unary)?;
+            return self.update(start, op, true, argument);
+        }
+        let op = match &self.peek().tok {
+            Tok::Punct(op @ ("!" | "-" | "+" | "~")) => op.to_string(),
+            Tok::Ident(op) if op == "typeof" || op == "void" || op == "delete" => op.clone(),
+            _ => return self.postfix(),
+        };
+        self.bump();
+
+        let argument = self.nested(Self::unary)?;
+        Ok(self.node(
+            "UnaryExpression",
+            start,
+            [
+                ("operator", op.into()),
+                ("prefix", Json::Bool(true)),
+                ("argument", argument),
+            ],
+        ))
+    }
+
+    /// A call or member expression, and a `++` or `--` after it on the same
+    /// line.
+    fn postfix(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.peek().start;
+        let argument = self.call()?;
+        match self.peek().tok {
+            Tok::Punct(op @ ("++" | "--")) if !self.peek().nl_before => {
+                self.bump();
+                self.update(start, op, false, argument)
+            }
+            _ => Ok(argument),
+        }
+    }
+
+    fn update(
+        &self,
+        start: usize,
+        op: &str,
+        prefix: bool,
+        argument: Json,
+    ) -> Result<Json, SyntaxError> {
+        if !is_target(&argument) {
+            return Err(SyntaxError::new(
+                self.src,
+                start,
+                format!("invalid `{}` operand", op),
+            ));
+        }
+        Ok(self.node(
+            "UpdateExpression",
+            start,
+            [
+                ("operator", op.into()),
+                ("prefix", prefix.into()),
+                ("argument", argument),
+            ],
+        ))
+    }
+
+    fn call(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.peek().start;
+        let mut callee = self.primary()?;
+
+        // each call or member access puts `callee` a level deeper
+        let depth = self.depth;
+        loop {
+            if self.eat_punct("(") {
+                let mut arguments = Vec::new();
+                while !self.is_punct(")") {
+                    arguments.push(self.assign()?);
+                    if !self.eat_punct(",") {
+                        break;
+                    }
+                }
+                self.expect_punct(")")?;
+                callee = self.node(
+                    "CallExpression",
+                    start,
+                    [
+                        ("callee", callee),
+                        ("arguments", arguments.into()),
+                        ("optional", Json::Bool(false)),
+                    ],
+                );
+            } else if self.eat_punct("[") {
+                let property = self.expression()?;
+                self.expect_punct("]")?;
+                callee = self.node(
+                    "MemberExpression",
+                    start,
+                    [
+                        ("object", callee),
+                        ("property", property),
+                        ("computed", Json::Bool(true)),
+                        ("optional", Json::Bool(false)),
+                    ],
+                );
+            } else if self.eat_punct(".") {
+                let property = self.property_name()?;
+                callee = self.node(
+                    "MemberExpression",
+                    start,
+                    [
+                        ("object", callee),
+                        ("property", property),
+                        ("computed", Json::Bool(false)),
+                        ("optional", Json::Bool(false)),
+                    ],
+                );
+            } else {
+                break;
+            }
+            self.descend()?;
+        }
+        self.depth = depth;
+
+        Ok(callee)
+    }
+
+    fn primary(&mut self) -> Result<Json, SyntaxError> {
+        let tok = self.peek().clone();
+        let raw = &self.src[tok.start..tok.end];
+        match &tok.tok {
+            Tok::Num(n) => {
+                self.bump();
+                // acorn's json has integral numbers without a fraction
+                let value = if n.fract() == 0. && n.abs() < 2f64.powi(53) {
+                    json!(*n as i64)
+                } else {
+                    json!(n)
+                };
+                Ok(self.node(
+                    "Literal",
+                    tok.start,
+                    [("value", value), ("raw", raw.into())],
+                ))
+            }
+            Tok::Str(s) => {
+                self.bump();
+                Ok(self.node(
+                    "Literal",
+                    tok.start,
+                    [("value", s.as_str().into()), ("raw", raw.into())],
+                ))
+            }
+            Tok::Ident(kw) if kw == "true" || kw == "false" || kw == "null" => {
+                self.bump();
+                let value = match kw.as_str() {
+                    "true" => json!(true),
+                    "false" => json!(false),
+                    _ => Json::Null,
+                };
+                Ok(self.node(
+                    "Literal",
+                    tok.start,
+                    [("value", value), ("raw", raw.into())],
+                ))
+            }
+            Tok::Template { .. } if raw.starts_with('`') => self.template(),
+            Tok::Ident(kw) if kw == "function" => self.function(false),
+            Tok::Ident(_) => self.ident(),
+            Tok::Punct("(") => {
+                self.bump();
+                let expr = self.expression()?;
+                self.expect_punct(")")?;
+                Ok(expr)
+            }
+            Tok::Punct("{") => self.object(),
+            Tok::Punct("[") => self.array(),
+            _ => Err(self.unexpected("an expression")),
+        }
+    }
+
+    /// An array literal, where a missing element is a hole, `null` in the
+    /// ESTree.
+    fn array(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.expect_punct("[")?.start;
+        let mut elements = Vec::new();
+        while !self.is_punct("]") {
+            if self.eat_punct(",") {
+                elements.push(Json::Null);
+                continue;
+            }
+            elements.push(self.assign()?);
+            if !self.eat_punct(",") {
+                break;
+            }
+        }
+        self.expect_punct("]")?;
+        Ok(self.node("ArrayExpression", start, [("elements", elements.into())]))
+    }
+
+    /// An object literal, whose properties are `key: value`, `[expr]: value`
+    /// or a lone name standing for `name: name`.
+    fn object(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.expect_punct("{")?.start;
+        let mut properties = Vec::new();
+        while !self.is_punct("}") {
+            let prop_start = self.peek().start;
+            let tok = self.peek().clone();
+            let (key, computed) = match &tok.tok {
+                Tok::Punct("[") => {
+                    self.bump();
+                    let key = self.assign()?;
+                    self.expect_punct("]")?;
+                    (key, true)
+                }
+                Tok::Num(_) | Tok::Str(_) => (self.primary()?, false),
+                _ => (self.property_name()?, false),
+            };
+
+            let shorthand = !computed && key["type"] == "Identifier" && !self.is_punct(":");
+            let value = if shorthand {
+                if let Tok::Ident(name) = &tok.tok {
+                    if RESERVED.contains(&name.as_str()) {
+                        return Err(self.unexpected("`:`"));
+                    }
+                }
+                key.clone()
+            } else {
+                self.expect_punct(":")?;
+                self.assign()?
+            };
+            properties.push(self.node(
+                "Property",
+                prop_start,
+                [
+                    ("method", Json::Bool(false)),
+                    ("shorthand", shorthand.into()),
+                    ("computed", computed.into()),
+                    ("key", key),
+                    ("value", value),
+                    ("kind", Json::from("init")),
+                ],
+            ));
+
+            if !self.eat_punct(",") {
+                break;
+            }
+        }
+        self.expect_punct("}")?;
+        Ok(self.node(
+            "ObjectExpression",
+            start,
+            [("properties", properties.into())],
+        ))
+    }
+
+    fn template(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.peek().start;
+        let mut quasis = Vec::new();
+        let mut expressions = Vec::new();
+        loop {
+            let tok = self.bump();
+            let (cooked, raw, tail) = match tok.tok {
+                Tok::Template { cooked, raw, tail } => (cooked, raw, tail),
+                _ => {
+                    return Err(SyntaxError::new(
+                        self.src,
+                        tok.start,
+                        "expected `}` ending the template substitution",
+                    ))
+                }
+            };
+            // the element is just the text between the delimiters
+            let delim = if tail { 1 } else { 2 };
+            quasis.push(json!({
+                "type": "TemplateElement",
+                "start": tok.start + 1,
+                "end": tok.end - delim,
+                "value": { "raw": raw, "cooked": cooked },
+                "tail": tail,
+            }));
+            if tail {
+                break;
+            }
+            expressions.push(self.expression()?);
+        }
+        Ok(self.node(
+            "TemplateLiteral",
+            start,
+            [
+                ("expressions", expressions.into()),
+                ("quasis", quasis.into()),
+            ],
+        ))
+    }
+
+    /// A function expression, or a function declaration statement, which
+    /// has to have a name.
+    fn function(&mut self, declaration: bool) -> Result<Json, SyntaxError> {
+        let start = self.bump().start;
+        let generator = self.eat_punct("*");
+        let id = if self.is_punct("(") && !declaration {
+            Json::Null
+        } else {
+            self.ident()?
+        };
+        let outer = std::mem::replace(&mut self.in_generator, generator);
+        let params = self.params()?;
+        let body = self.block()?;
+        self.in_generator = outer;
+
+        let ty = match declaration {
+            true => "FunctionDeclaration",
+            false => "FunctionExpression",
+        };
+        Ok(self.node(
+            ty,
+            start,
+            [
+                ("id", id),
+                ("expression", Json::Bool(false)),
+                ("generator", generator.into()),
+                ("async", Json::Bool(false)),
+                ("params", params.into()),
+                ("body", body),
+            ],
+        ))
+    }
+
+    /// A parenthesised parameter list.
+    fn params(&mut self) -> Result<Vec<Json>, SyntaxError> {
+        self.expect_punct("(")?;
+        let mut params = Vec::new();
+        while !self.is_punct(")") {
+            let param = self.param()?;
+            let rest = param["type"] == "RestElement";
+            params.push(param);
+            if rest && !self.is_punct(")") {
+                return Err(self.unexpected("`)` after the rest parameter"));
+            }
+            if !self.eat_punct(",") {
+                break;
+            }
+        }
+        self.expect_punct(")")?;
+        Ok(params)
+    }
+
+    /// One parameter or element of an array pattern: a binding, optionally
+    /// with a default, or `...` and a binding.
+    fn param(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.peek().start;
+        if self.eat_punct("...") {
+            let argument = self.binding()?;
+            return Ok(self.node("RestElement", start, [("argument", argument)]));
+        }
+
+        let left = self.binding()?;
+        self.with_default(start, left)
+    }
+
+    /// `left`, or an `AssignmentPattern` giving it a default if one follows.
+    fn with_default(&mut self, start: usize, left: Json) -> Result<Json, SyntaxError> {
+        if !self.eat_punct("=") {
+            return Ok(left);
+        }
+        let right = self.assign()?;
+        Ok(self.node(
+            "AssignmentPattern",
+            start,
+            [("left", left), ("right", right)],
+        ))
+    }
+
+    /// What a declaration or parameter binds: a name, or an object or array
+    /// pattern.
+    fn binding(&mut self) -> Result<Json, SyntaxError> {
+        self.nested(Self::binding_kind)
+    }
+
+    fn binding_kind(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.peek().start;
+        if self.eat_punct("[") {
+            let mut elements = Vec::new();
+            while !self.is_punct("]") {
+                if self.eat_punct(",") {
+                    elements.push(Json::Null);
+                    continue;
+                }
+                let elem = self.param()?;
+                let rest = elem["type"] == "RestElement";
+                elements.push(elem);
+                if rest && !self.is_punct("]") {
+                    return Err(self.unexpected("`]` after the rest element"));
+                }
+                if !self.eat_punct(",") {
+                    break;
+                }
+            }
+            self.expect_punct("]")?;
+            return Ok(self.node("ArrayPattern", start, [("elements", elements.into())]));
+        }
+        if !self.is_punct("{") {
+            return self.ident();
+        }
+
+        self.bump();
+        let mut properties = Vec::new();
+        while !self.is_punct("}") {
+            let prop_start = self.peek().start;
+            if self.eat_punct("...") {
+                let argument = self.ident()?;
+                properties.push(self.node("RestElement", prop_start, [("argument", argument)]));
+                if !self.is_punct("}") {
+                    return Err(self.unexpected("`}` after the rest element"));
+                }
+                break;
+            }
+
+            let (key, computed) = match &self.peek().tok {
+                Tok::Punct("[") => {
+                    self.bump();
+                    let key = self.assign()?;
+                    self.expect_punct("]")?;
+                    (key, true)
+                }
+                Tok::Num(_) | Tok::Str(_) => (self.primary()?, false),
+                _ => (self.property_name()?, false),
+            };
+            let shorthand = !computed && key["type"] == "Identifier" && !self.is_punct(":");
+            let value = if shorthand {
+                // the key is also the name bound
+                let name = key["name"].as_str().unwrap_or_default();
+                if RESERVED.contains(&name) {
+                    return Err(self.unexpected("`:`"));
+                }
+                self.with_default(prop_start, key.clone())?
+            } else {
+                self.expect_punct(":")?;
+                let value_start = self.peek().start;
+                let value = self.binding()?;
+                self.with_default(value_start, value)?
+            };
+            properties.push(self.node(
+                "Property",
+                prop_start,
+                [
+                    ("method", Json::Bool(false)),
+                    ("shorthand", shorthand.into()),
+                    ("computed", computed.into()),
+                    ("key", key),
+                    ("value", value),
+                    ("kind", Json::from("init")),
+                ],
+            ));
+
+            if !self.eat_punct(",") {
+                break;
+            }
+        }
+        self.expect_punct("}")?;
+        Ok(self.node("ObjectPattern", start, [("properties", properties.into())]))
+    }
+
+    /// Whether the next tokens start an arrow function, `x =>` or `(...) =>`.
+    fn arrow_ahead(&self) -> bool {
+        let arrow_at = |n: usize| {
+            let tok = self.peek_at(n);
+            tok.tok == Tok::Punct("=>") && !tok.nl_before
+        };
+
+        match self.peek().tok {
+            Tok::Ident(_) => arrow_at(1),
+            Tok::Punct("(") => {
+                let mut depth = 0;
+                for (n, tok) in self.toks[self.pos..].iter().enumerate() {
+                    match tok.tok {
+                        Tok::Punct("(" | "[" | "{") => depth += 1,
+                        Tok::Punct(")" | "]" | "}") => {
+                            depth -= 1;
+                            if depth == 0 {
+                                return arrow_at(n + 1);
+                            }
+                        }
+                        Tok::Eof => return false,
+                        _ => {}
+                    }
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn arrow(&mut self) -> Result<Json, SyntaxError> {
+        let start = self.peek().start;
+        // an arrow function is never a generator
+        let outer = std::mem::replace(&mut self.in_generator, false);
+        let params = if self.is_punct("(") {
+            self.params()?
+        } else {
+            vec![self.ident()?]
+        };
+        self.expect_punct("=>")?;
+
+        let (expression, body) = if self.is_punct("{") {
+            (false, self.block()?)
+        } else {
+            (true, self.assign()?)
+        };
+        self.in_generator = outer;
+
+        Ok(self.node(
+            "ArrowFunctionExpression",
+            start,
+            [
+                ("id", Json::Null),
+                ("expression", expression.into()),
+                ("generator", Json::Bool(false)),
+                ("async", Json::Bool(false)),
+                ("params", params.into()),
+                ("body", body),
+            ],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::ast::Program;
+
+    /// The s-expression of the program `source` parses to.
+    fn sexp(source: &str) -> String {
+        let json = parse(source).expect("test source parses");
+        Program::from_estree(&json)
+            .expect("test source is supported")
+            .to_string()
+    }
+
+    /// Where and why `source` fails to parse.
+    fn error(source: &str) -> (usize, usize, String) {
+        let err = parse(source).expect_err(source);
+        (err.line, err.column, err.msg)
+    }
+
+    #[test]
+    fn programs() {
+        for (source, expected) in [
+            ("a + b; c", "(seq (arithmetic + a b) c)"),
+            (
+                "let a = 1, [b, ...c] = d, {e: f = 2, ...g} = h",
+                "(let a = (value (number 1)), (array-pattern b (rest c)) = d, \
+                 (object-pattern [e (default f (value (number 2)))] (rest g)) = h, (value ()))",
+            ),
+            (
+                "if (a) b; else if (c) d",
+                "(conditional a b (conditional c d (value ())))",
+            ),
+            ("(a, b) => ({a})", "(fn (a b) (object [a a]))"),
+            (
+                "const f = function* () { yield* g(); yield; }",
+                "(let f = (fn* () (seq (yield* (call g)) (yield))), (value ()))",
+            ),
+            (
+                "for (const [k, v] of xs) { continue; }",
+                "(for-of (const (array-pattern k v)) xs (continue))",
+            ),
+            ("l: for (;;) break l", "(l: for () () () (break l))"),
+            (
+                "try { throw 1 } catch { } finally { }",
+                "(try (throw (value (number 1))) (catch (value ())) (finally (value ())))",
+            ),
+            (
+                "!a.b(c)[d]",
+                "(unary ! (member (call (member a b) c) (computed d)))",
+            ),
+        ] {
+            assert_eq!(sexp(source), expected, "parsing {}", source);
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(
+            sexp("1 + 2 * 3"),
+            "(arithmetic + (value (number 1)) (arithmetic * (value (number 2)) (value (number 3))))"
+        );
+        assert_eq!(sexp("x && y || z"), "(logical || (logical && x y) z)");
+        assert_eq!(
+            sexp("a < b == c > d"),
+            "(relational == (relational < a b) (relational > c d))"
+        );
+        assert_eq!(sexp("-a.b"), "(unary - (member a b))");
+    }
+
+    #[test]
+    fn associativity() {
+        assert_eq!(
+            sexp("1 - 2 - 3"),
+            "(arithmetic - (arithmetic - (value (number 1)) (value (number 2))) (value (number 3)))"
+        );
+        assert_eq!(
+            sexp("a = b = 1"),
+            "(assign a (assign b (value (number 1))))"
+        );
+        assert_eq!(
+            sexp("a ? b : c ? d : e"),
+            "(conditional a b (conditional c d e))"
+        );
+        // `**` isn't evaluated, so check the estree
+        let json = parse("2 ** 3 ** 2").expect("test source parses");
+        let expr = &json["body"][0]["expression"];
+        assert_eq!(expr["left"]["value"], 2.);
+        assert_eq!(expr["right"]["operator"], "**");
+    }
+
+    #[test]
+    fn automatic_semicolons() {
+        assert_eq!(
+            sexp("let x = 1\nlet y = x\ny"),
+            "(let x = (value (number 1)), y = x, y)"
+        );
+        assert_eq!(
+            sexp("return\n1"),
+            "(seq (return (value ())) (value (number 1)))"
+        );
+        assert_eq!(sexp("x\n++y"), "(seq x (prefix ++ y))");
+        assert_eq!(sexp("while (x) { x-- }"), "(while x (postfix -- x))");
+        assert_eq!(
+            sexp("do x++; while (x < 3) y"),
+            "(seq (do-while (relational < x (value (number 3))) (postfix ++ x)) y)"
+        );
+        // no semicolon goes before a line starting with `(`
+        assert_eq!(sexp("a\n(b)"), "(call a b)");
+    }
+
+    #[test]
+    fn templates() {
+        assert_eq!(
+            sexp("`a${1 + 1}b${`c${2}`}`"),
+            "(template \"a\" (arithmetic + (value (number 1)) (value (number 1))) \"b\" \
+             (template \"c\" (value (number 2)) \"\") \"\")"
+        );
+        assert_eq!(
+            sexp("`x${ {a: 1}.a }`"),
+            "(template \"x\" (member (object [a (value (number 1))]) a) \"\")"
+        );
+        let json = parse(r"`\n`").expect("test source parses");
+        let quasi = &json["body"][0]["expression"]["quasis"][0]["value"];
+        assert_eq!(
+            (&quasi["cooked"], &quasi["raw"]),
+            (&"\n".into(), &r"\n".into())
+        );
+    }
+
+    #[test]
+    fn syntax_error_positions() {
+        for (source, line, column, msg) in [
+            ("let = ;", 1, 5, "expected an identifier, found `=`"),
+            ("let x = 1 let y = 2", 1, 11, "expected `;`, found `let`"),
+            ("1 = 2", 1, 1, "invalid assignment target"),
+            (
+                "throw\n1",
+                2,
+                1,
+                "expected an expression on the same line as `throw`, found `1`",
+            ),
+            ("foo(1,\n  2", 2, 4, "expected `)`, found end of input"),
+            ("`abc", 1, 1, "unterminated template"),
+            ("é + ;", 1, 5, "expected an expression, found `;`"),
+        ] {
+            assert_eq!(
+                error(source),
+                (line, column, msg.to_owned()),
+                "parsing {}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn long_chains() {
+        // each term nests the tree a level deeper, which once took time and
+        // stack in proportion to the square of the length
+        let sum = vec!["1"; 900].join(" + ");
+        let json = parse(&sum).expect("a long chain parses");
+        let program = Program::from_estree(&json).expect("a long chain is supported");
+        let val = program
+            .eval_with(Default::default())
+            .expect("a long chain evaluates");
+        assert_eq!(val.to_string(), "(value (number 900))");
+
+        let calls = format!("f{}", "(1).a".repeat(400));
+        assert!(parse(&calls).is_ok());
+    }
+
+    #[test]
+    fn deep_nesting_is_a_syntax_error() {
+        let parens = format!("{}1{}", "(".repeat(3000), ")".repeat(3000));
+        assert_eq!(
+            error(&parens),
+            (1, 1000, "nested more than 1000 deep".to_owned())
+        );
+
+        let sum = vec!["1"; 3000].join(" + ");
+        assert_eq!(error(&sum).2, "nested more than 1000 deep");
+
+        let blocks = format!("{}{}", "{".repeat(3000), "}".repeat(3000));
+        assert_eq!(error(&blocks).2, "nested more than 1000 deep");
+    }
+
+    #[test]
+    fn function_declarations_are_rejected_as_from_acorn() {
+        let json = parse("function f() { return 1 }").expect("a function declaration parses");
+        assert_eq!(json["body"][0]["type"], "FunctionDeclaration");
+        assert!(Program::from_estree(&json).is_err());
+
+        assert_eq!(
+            error("function () {}"),
+            (1, 10, "expected an identifier, found `(`".to_owned())
+        );
+    }
+}
@@ -0,0 +1,117 @@
+/// A range of the program's source, as `start` and `end` offsets taken from
+/// the ESTree input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// This span, given in utf-16 code units of `source` as `acorn` gives
+    /// offsets, in bytes of it instead.
+    pub fn from_utf16(self, source: &str) -> Span {
+        Span {
+            start: utf16_to_byte(source, self.start),
+            end: utf16_to_byte(source, self.end),
+        }
+    }
+
+    /// Render `msg` rustc style, with the line of `source` this span starts
+    /// on and a caret underline beneath the spanned part of it:
+    ///
+    /// ```text
+    /// error: divide by zero in `/` of (value (number 1))
+    ///  --> 2:9
+    ///   |
+    /// 2 | let y = 1 / 0;
+    ///   |         ^^^^^
+    /// ```
+    ///
+    /// Spans running past the end of their first line are underlined to the
+    /// end of it.
+    pub fn render(&self, source: &str, msg: &str) -> String {
+        format!("error: {}\n{}", msg, self.snippet(source))
+    }
+
+    /// Just the location and underlined source line of `render`.
+    pub fn snippet(&self, source: &str) -> String {
+        let start = floor_char_boundary(source, self.start);
+        let end = floor_char_boundary(source, self.end.max(self.start));
+
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        let line = source[line_start..line_end].trim_end_matches('\r');
+        let line_no = source[..start].matches('\n').count() + 1;
+
+        let col = source[line_start..start].chars().count();
+        let width = source[start..end.min(line_end)].chars().count().max(1);
+        let pad = " ".repeat(line_no.to_string().len());
+
+        format!(
+            "{pad}--> {line_no}:{col}\n{pad} |\n{line_no} | {line}\n{pad} | {}{}",
+            " ".repeat(col),
+            "^".repeat(width),
+            col = col + 1,
+        )
+    }
+}
+
+/// The byte offset into `source` of the char `offset` utf-16 code units in,
+/// or the end of `source` if it's shorter than that.
+fn utf16_to_byte(source: &str, offset: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in source.char_indices() {
+        if units >= offset {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    source.len()
+}
+
+fn floor_char_boundary(s: &str, mut i: usize) -> usize {
+    i = i.min(s.len());
+    while !s.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Span;
+
+    #[test]
+    fn utf16_offsets_become_bytes() {
+        // `😀` is two utf-16 code units and four bytes, `é` one and two
+        let source = "'😀é' + x";
+        let span = Span { start: 8, end: 9 }.from_utf16(source);
+        assert_eq!(&source[span.start..span.end], "x");
+        assert_eq!(
+            Span {
+                start: 99,
+                end: 100
+            }
+            .from_utf16(source)
+            .start,
+            source.len()
+        );
+    }
+
+    #[test]
+    fn snippet_counts_columns_in_chars() {
+        let source = "let s = 'é';
+s + 1 / 0";
+        let start = source.find('1').expect("test source has a 1");
+        let span = Span {
+            start,
+            end: start + 5,
+        };
+        assert_eq!(
+            span.snippet(source),
+            " --> 2:5\n  |\n2 | s + 1 / 0\n  |     ^^^^^"
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use crate::ast::Value;
+
+/// Where a binding's value lives in a `Store`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Loc(usize);
+
+/// The values of the bindings made during an evaluation, by location.
+///
+/// Environments, and the closures holding them, only refer to locations,
+/// so a closure stored in a binding it can see doesn't keep itself alive.
+/// Locations are freed when the scope binding them ends with nothing left
+/// that can see them, and the rest go with the store at the end of the
+/// evaluation.
+#[derive(Debug, Default)]
+pub(crate) struct Store {
+    vals: Vec<Option<Value>>,
+    /// Freed locations, to be handed out again.
+    free: Vec<Loc>,
+}
+
+impl Store {
+    /// A new location holding `val`, or nothing for a binding declared but
+    /// not yet initialized.
+    pub fn alloc(&mut self, val: Option<Value>) -> Loc {
+        match self.free.pop() {
+            Some(loc) => {
+                self.vals[loc.0] = val;
+                loc
+            }
+            None => {
+                self.vals.push(val);
+                Loc(self.vals.len() - 1)
+            }
+        }
+    }
+
+    /// Give `loc` back to be allocated again, along with its value.
+    pub fn free(&mut self, loc: Loc) -> Option<Value> {
+        self.free.push(loc);
+        self.vals[loc.0].take()
+    }
+
+    /// The value at `loc`, or `None` if it hasn't been initialized.
+    pub fn get(&self, loc: Loc) -> Option<&Value> {
+        self.vals[loc.0].as_ref()
+    }
+
+    pub fn set(&mut self, loc: Loc, val: Value) {
+        self.vals[loc.0] = Some(val);
+    }
+}